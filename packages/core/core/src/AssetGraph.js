@@ -276,11 +276,13 @@ export default class AssetGraph extends ContentGraph<AssetGraphNode> {
     dependency: Dependency,
     assetGroup: ?AssetGroup,
     correspondingRequest: string,
+    isUnresolved: boolean = false,
   ) {
     let depNodeId = this.getNodeIdByContentKey(dependency.id);
     let depNode = nullthrows(this.getNode(depNodeId));
     invariant(depNode.type === 'dependency');
     depNode.correspondingRequest = correspondingRequest;
+    depNode.isUnresolved = isUnresolved;
 
     if (!assetGroup) {
       return;
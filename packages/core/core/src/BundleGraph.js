@@ -428,6 +428,12 @@ export default class BundleGraph {
     return !!node.hasDeferred || node.excluded;
   }
 
+  isDependencyUnresolved(dependency: Dependency): boolean {
+    let node = this._graph.getNodeByContentKey(dependency.id);
+    invariant(node && node.type === 'dependency');
+    return node.isUnresolved === true;
+  }
+
   getParentBundlesOfBundleGroup(bundleGroup: BundleGroup): Array<Bundle> {
     return this._graph
       .getNodeIdsConnectedTo(
@@ -53,6 +53,7 @@ export function createEnvironment({
       case 'browser':
       case 'web-worker':
       case 'service-worker':
+      case 'web-extension':
       case 'electron-renderer':
         engines = {
           browsers: DEFAULT_ENGINES.browsers,
@@ -86,6 +87,9 @@ export function createEnvironment({
       case 'electron-renderer':
         outputFormat = 'commonjs';
         break;
+      case 'deno':
+        outputFormat = 'esmodule';
+        break;
       default:
         outputFormat = 'global';
         break;
@@ -77,6 +77,12 @@ export default class BundleGraph<TBundle: IBundle>
     return this.#graph.isDependencySkipped(dependencyToInternalDependency(dep));
   }
 
+  isDependencyUnresolved(dep: IDependency): boolean {
+    return this.#graph.isDependencyUnresolved(
+      dependencyToInternalDependency(dep),
+    );
+  }
+
   getResolvedAsset(dep: IDependency, bundle: ?IBundle): ?IAsset {
     let resolution = this.#graph.getResolvedAsset(
       dependencyToInternalDependency(dep),
@@ -24,6 +24,7 @@ export const BROWSER_ENVS: Set<string> = new Set<string>([
   'web-worker',
   'service-worker',
   'worklet',
+  'web-extension',
   'electron-renderer',
 ]);
 const ELECTRON_ENVS = new Set(['electron-main', 'electron-renderer']);
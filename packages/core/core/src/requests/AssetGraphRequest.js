@@ -18,7 +18,7 @@ import type {
 } from '../types';
 import type {StaticRunOpts, RunAPI} from '../RequestTracker';
 import type {EntryResult} from './EntryRequest';
-import type {PathRequestInput} from './PathRequest';
+import type {PathRequestInput, PathRequestResult} from './PathRequest';
 
 import invariant from 'assert';
 import nullthrows from 'nullthrows';
@@ -854,11 +854,16 @@ export class AssetGraphBuilder {
 
   async runPathRequest(input: Dependency) {
     let request = createPathRequest({dependency: input, name: this.name});
-    let result = await this.api.runRequest<PathRequestInput, ?AssetGroup>(
-      request,
-      {force: true},
+    let result = await this.api.runRequest<
+      PathRequestInput,
+      PathRequestResult,
+    >(request, {force: true});
+    this.assetGraph.resolveDependency(
+      input,
+      result.assetGroup,
+      request.id,
+      result.isUnresolved,
     );
-    this.assetGraph.resolveDependency(input, result, request.id);
   }
 
   async runAssetRequest(input: AssetGroup) {
@@ -28,10 +28,16 @@ import {Priority} from '../types';
 export type PathRequest = {|
   id: string,
   +type: 'path_request',
-  run: RunOpts => Async<?AssetGroup>,
+  run: RunOpts => Async<PathRequestResult>,
   input: PathRequestInput,
 |};
 
+export type PathRequestResult = {|
+  assetGroup: ?AssetGroup,
+  // An optional dependency that no resolver could resolve, as opposed to one that was excluded.
+  isUnresolved?: boolean,
+|};
+
 export type PathRequestInput = {|
   dependency: Dependency,
   name: string,
@@ -91,7 +97,7 @@ async function run({input, api, options}: RunOpts) {
 
   if (result.assetGroup) {
     api.invalidateOnFileDelete(result.assetGroup.filePath);
-    return result.assetGroup;
+    return {assetGroup: result.assetGroup};
   }
 
   if (result.diagnostics && result.diagnostics.length > 0) {
@@ -100,6 +106,8 @@ async function run({input, api, options}: RunOpts) {
     err.code = 'MODULE_NOT_FOUND';
     throw err;
   }
+
+  return {assetGroup: null, isUnresolved: result.isUnresolved};
 }
 
 type ResolverRunnerOpts = {|
@@ -113,6 +121,7 @@ type ResolverResult = {|
   invalidateOnFileChange?: Array<FilePath>,
   invalidateOnEnvChange?: Array<string>,
   diagnostics?: Array<Diagnostic>,
+  isUnresolved?: boolean,
 |};
 
 export class ResolverRunner {
@@ -301,6 +310,7 @@ export class ResolverRunner {
         invalidateOnFileCreate,
         invalidateOnFileChange,
         invalidateOnEnvChange,
+        isUnresolved: true,
       };
     }
 
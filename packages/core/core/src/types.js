@@ -315,6 +315,8 @@ export type DependencyNode = {|
   usedSymbolsUpDirtyDown: boolean,
   /** dependency was excluded (= no used symbols (globally) & side-effect free) */
   excluded: boolean,
+  /** optional dependency that no resolver could resolve */
+  isUnresolved?: boolean,
 |};
 
 export type RootNode = {|id: ContentKey, +type: 'root', value: string | null|};
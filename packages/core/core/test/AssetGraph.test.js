@@ -282,6 +282,55 @@ describe('AssetGraph', () => {
     assert(graph.hasEdge(dependencyNodeId, assetGroupNodeId2));
   });
 
+  it('resolveDependency should record whether an optional dependency was unresolved', () => {
+    let graph = new AssetGraph();
+    graph.setRootConnections({
+      targets: DEFAULT_TARGETS,
+      entries: [toProjectPath('/path/to/index')],
+    });
+
+    graph.resolveEntry(
+      toProjectPath('/path/to/index'),
+      [
+        {
+          filePath: toProjectPath('/path/to/index/src/main.js'),
+          packagePath: toProjectPath('/path/to/index'),
+        },
+      ],
+      '1',
+    );
+    graph.resolveTargets(
+      {
+        filePath: toProjectPath('/path/to/index/src/main.js'),
+        packagePath: toProjectPath('/path/to/index'),
+      },
+      DEFAULT_TARGETS,
+      '2',
+    );
+
+    let dep = createDependency({
+      specifier: 'path/to/index/src/main.js',
+      specifierType: 'esm',
+      target: DEFAULT_TARGETS[0],
+      env: DEFAULT_ENV,
+    });
+    let getDependencyNode = () => {
+      let node = graph.getNodeByContentKey(dep.id);
+      invariant(node?.type === 'dependency');
+      return node;
+    };
+
+    graph.resolveDependency(dep, null, '3', true);
+    assert.equal(getDependencyNode().isUnresolved, true);
+
+    graph.resolveDependency(
+      dep,
+      {filePath: toProjectPath('/index.js'), env: DEFAULT_ENV},
+      '4',
+    );
+    assert.equal(getDependencyNode().isUnresolved, false);
+  });
+
   it('resolveAssetGroup should update the asset and dep nodes a file is connected to', () => {
     let graph = new AssetGraph();
     graph.setRootConnections({
@@ -96,4 +96,14 @@ describe('Environment', () => {
       sourceType: 'module',
     });
   });
+
+  it('assigns defaults for web extensions and Deno', () => {
+    let webExtension = createEnvironment({context: 'web-extension'});
+    assert.deepEqual(webExtension.engines, {browsers: ['> 0.25%']});
+    assert.equal(webExtension.outputFormat, 'global');
+
+    let deno = createEnvironment({context: 'deno'});
+    assert.deepEqual(deno.engines, {});
+    assert.equal(deno.outputFormat, 'esmodule');
+  });
 });
@@ -111,9 +111,11 @@ export type EnvironmentContext =
   | 'web-worker'
   | 'service-worker'
   | 'worklet'
+  | 'web-extension'
   | 'node'
   | 'electron-main'
-  | 'electron-renderer';
+  | 'electron-renderer'
+  | 'deno';
 
 /** The JS module format for the bundle output */
 export type OutputFormat = 'esmodule' | 'commonjs' | 'global';
@@ -1392,6 +1394,8 @@ export interface BundleGraph<TBundle: Bundle> {
   );
   /** Returns whether a dependency was excluded because it had no used symbols. */
   isDependencySkipped(dependency: Dependency): boolean;
+  /** Returns whether an optional dependency could not be resolved. */
+  isDependencyUnresolved(dependency: Dependency): boolean;
   /**
    * Returns the asset that the dependency resolved to.
    * If a bundle is given, assets in that bundle are preferred.
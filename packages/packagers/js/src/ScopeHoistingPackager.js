@@ -46,6 +46,11 @@ const GLOBALS_BY_CONTEXT = {
     ...Object.keys(globals.serviceworker),
   ]),
   worklet: new Set([...BUILTINS]),
+  'web-extension': new Set([
+    ...BUILTINS,
+    ...Object.keys(globals.browser),
+    ...Object.keys(globals.webextensions),
+  ]),
   node: new Set([...BUILTINS, ...Object.keys(globals.node)]),
   'electron-main': new Set([...BUILTINS, ...Object.keys(globals.node)]),
   'electron-renderer': new Set([
@@ -53,6 +58,7 @@ const GLOBALS_BY_CONTEXT = {
     ...Object.keys(globals.node),
     ...Object.keys(globals.browser),
   ]),
+  deno: new Set([...BUILTINS, ...Object.keys(globals.worker), 'Deno']),
 };
 
 const OUTPUT_FORMATS = {
@@ -593,10 +599,12 @@ ${code}
             // be wrapped in Promise.resolve() later.
             asyncResolution.value
           : this.bundleGraph.getResolvedAsset(dep, this.bundle);
+      // Lazy external dependencies are kept as native import() calls in the code.
       if (
         !resolved &&
         !dep.isOptional &&
-        !this.bundleGraph.isDependencySkipped(dep)
+        !this.bundleGraph.isDependencySkipped(dep) &&
+        !(dep.priority === 'lazy' && dep.meta.isExternal === true)
       ) {
         let external = this.addExternal(dep);
         for (let [imported, {local}] of dep.symbols) {
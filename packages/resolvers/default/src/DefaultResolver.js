@@ -2,6 +2,7 @@
 
 import {Resolver} from '@parcel/plugin';
 import NodeResolver from '@parcel/node-resolver-core';
+import ThrowableDiagnostic from '@parcel/diagnostic';
 
 // Throw user friendly errors on special webpack loader syntax
 // ex. `imports-loader?$=jquery!./example.js`
@@ -15,6 +16,36 @@ export default (new Resolver({
       );
     }
 
+    // import.meta.glob and require.context create glob dependencies, which are
+    // expanded by the glob resolver. It runs before this one when configured.
+    if (dependency.meta?.glob != null) {
+      throw new ThrowableDiagnostic({
+        diagnostic: {
+          message: `Glob dependency ${dependency.specifier} could not be resolved because the glob resolver is not configured.`,
+          codeFrames: dependency.loc
+            ? [
+                {
+                  codeHighlights: [
+                    {
+                      start: dependency.loc.start,
+                      end: dependency.loc.end,
+                    },
+                  ],
+                },
+              ]
+            : undefined,
+          hints: [
+            'Add "@parcel/resolver-glob" before the default resolvers in your .parcelrc: "resolvers": ["@parcel/resolver-glob", "..."]',
+          ],
+        },
+      });
+    }
+
+    // Dependencies marked as external by a transformer are loaded at runtime.
+    if (dependency.meta?.isExternal === true) {
+      return {isExcluded: true};
+    }
+
     const resolver = new NodeResolver({
       fs: options.inputFS,
       projectRoot: options.projectRoot,
@@ -114,9 +114,14 @@ export default (new Resolver({
       specifier = path.resolve(path.dirname(sourceFile), specifier);
     }
 
+    // Options from import.meta.glob or require.context calls in JS.
+    let globOptions: ?GlobOptions = (dependency.meta?.glob: any);
     let normalized = normalizeSeparators(specifier);
     let files = await glob(normalized, options.inputFS, {
       onlyFiles: true,
+      ignore: globOptions?.ignore.map(ignore =>
+        normalizeSeparators(path.resolve(path.dirname(sourceFile), ignore)),
+      ),
     });
 
     let dir = path.dirname(specifier);
@@ -130,7 +135,9 @@ export default (new Resolver({
     });
 
     let code = '';
-    if (sourceAssetType === 'js') {
+    if (sourceAssetType === 'js' && globOptions != null) {
+      code = generateGlobMap(files, dir, sourceFile, specifier, globOptions);
+    } else if (sourceAssetType === 'js') {
       let re = globToRegex(normalized, {capture: true});
       let matches = {};
       for (let [file, relative] of results) {
@@ -166,6 +173,63 @@ export default (new Resolver({
   },
 }): Resolver);
 
+type GlobOptions = {|
+  eager: boolean,
+  import: ?string,
+  query: ?string,
+  ignore: Array<string>,
+  filter: ?[string, string],
+  context: boolean,
+|};
+
+// Generates a flat map of matched files, keyed by their path relative to the importing
+// file (import.meta.glob) or the context directory (require.context).
+function generateGlobMap(
+  files: Array<string>,
+  dir: string,
+  sourceFile: string,
+  specifier: string,
+  globOptions: GlobOptions,
+): string {
+  let keyBase = globOptions.context
+    ? specifier.replace(/[/\\](\*\*[/\\])?\*$/, '')
+    : path.dirname(sourceFile);
+  let filter = globOptions.filter
+    ? new RegExp(globOptions.filter[0], globOptions.filter[1])
+    : null;
+
+  let entries = [];
+  for (let file of files.sort()) {
+    let key = relativePath(keyBase, file);
+    if (filter && !filter.test(key)) {
+      continue;
+    }
+
+    let request = JSON.stringify(
+      relativePath(dir, file) + (globOptions.query ?? ''),
+    );
+    let member =
+      globOptions.import != null ? `[${JSON.stringify(globOptions.import)}]` : '';
+    let value = globOptions.eager
+      ? `require(${request})${member}`
+      : `() => import(${request})` + (member ? `.then(m => m${member})` : '');
+    entries.push(`  ${JSON.stringify(key)}: ${value}`);
+  }
+
+  let map = `{\n${entries.join(',\n')}\n}`;
+  if (!globOptions.context) {
+    return `module.exports = ${map};`;
+  }
+
+  return `var map = ${map};
+function context(key) {
+  return map[key]${globOptions.eager ? '' : '()'};
+}
+context.keys = () => Object.keys(map);
+context.resolve = key => key;
+module.exports = context;`;
+}
+
 function set(obj, path, value) {
   for (let i = 0; i < path.length - 1; i++) {
     let part = path[i];
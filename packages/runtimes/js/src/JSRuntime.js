@@ -84,6 +84,23 @@ export default (new Runtime({
     for (let dependency of asyncDependencies) {
       let resolved = bundleGraph.resolveAsyncDependency(dependency, bundle);
       if (resolved == null) {
+        // Optional dynamic imports that failed to resolve reject at runtime,
+        // so they can be handled by the surrounding try/catch or .catch().
+        // Excluded and external dependencies are left to be loaded at runtime.
+        if (
+          dependency.isOptional &&
+          dependency.priority === 'lazy' &&
+          bundleGraph.isDependencyUnresolved(dependency)
+        ) {
+          assets.push({
+            filePath: __filename,
+            code: `module.exports = Promise.reject(new Error(${JSON.stringify(
+              `Cannot find module '${dependency.specifier}'`,
+            )}));`,
+            dependency,
+            env: {sourceType: 'module'},
+          });
+        }
         continue;
       }
 
@@ -427,20 +444,13 @@ function getHintedBundleGroups(
   let prefetch = [];
   let {asyncDependencies} = getDependencies(bundle);
   for (let dependency of asyncDependencies) {
-    let attributes = dependency.meta?.importAttributes;
-    if (
-      typeof attributes === 'object' &&
-      attributes != null &&
-      // $FlowFixMe
-      (attributes.preload || attributes.prefetch)
-    ) {
+    let resourceHint = dependency.meta?.resourceHint;
+    if (resourceHint === 'preload' || resourceHint === 'prefetch') {
       let resolved = bundleGraph.resolveAsyncDependency(dependency, bundle);
       if (resolved?.type === 'bundle_group') {
-        // === true for flow
-        if (attributes.preload === true) {
+        if (resourceHint === 'preload') {
           preload.push(resolved.value);
-        }
-        if (attributes.prefetch === true) {
+        } else {
           prefetch.push(resolved.value);
         }
       }
@@ -4,14 +4,15 @@ use std::fmt;
 use std::path::Path;
 
 use serde::{Deserialize, Serialize};
-use swc_atoms::JsWord;
-use swc_common::{Mark, SourceMap, Span, DUMMY_SP};
+use swc_atoms::{js_word, JsWord};
+use swc_common::comments::{Comments, SingleThreadedComments};
+use swc_common::{BytePos, Mark, SourceMap, Span, Spanned, DUMMY_SP};
 use swc_ecmascript::ast::{self, Callee, Id, MemberProp};
-use swc_ecmascript::visit::{Fold, FoldWith};
+use swc_ecmascript::visit::{Fold, FoldWith, Visit, VisitWith};
 
-use crate::fold_member_expr_skip_prop;
 use crate::utils::*;
-use crate::Config;
+use crate::{fold_member_expr_skip_prop, fold_statement_span};
+use crate::{targets_to_versions, Config};
 
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
@@ -32,8 +33,12 @@ pub enum DependencyKind {
   WebWorker,
   ServiceWorker,
   Worklet,
+  ImportScripts,
   Url,
   File,
+  NodeWorker,
+  ChildProcess,
+  Glob,
 }
 
 impl fmt::Display for DependencyKind {
@@ -48,16 +53,180 @@ pub struct DependencyDescriptor {
   pub loc: SourceLocation,
   /// The text specifier associated with the import/export statement.
   pub specifier: swc_atoms::JsWord,
-  pub attributes: Option<HashMap<swc_atoms::JsWord, bool>>,
+  pub attributes: Option<HashMap<swc_atoms::JsWord, swc_atoms::JsWord>>,
   pub is_optional: bool,
   pub is_helper: bool,
   pub source_type: Option<SourceType>,
   pub placeholder: Option<String>,
+  /// Options for `Glob` dependencies, where the specifier is the glob pattern.
+  pub glob: Option<GlobOptions>,
+  /// A bundle name hint from a magic comment, e.g. `/* webpackChunkName: "admin" */`.
+  pub bundle_name: Option<JsWord>,
+  pub priority: Priority,
+  /// Whether the bundle should be preloaded or prefetched by its parent.
+  pub resource_hint: Option<ResourceHint>,
+  /// The worklet a `Worklet` dependency is added to.
+  pub worklet_type: Option<WorkletType>,
+  pub specifier_type: SpecifierType,
+  /// Whether the dependency should be left for the runtime to load rather than bundled.
+  pub is_external: bool,
+  /// Whether the specifier explicitly refers to a Node builtin, e.g. `node:fs`.
+  /// The prefix is kept in the specifier even if it is stripped from the code.
+  pub is_builtin: bool,
+  /// The specifier as written in the code, if it was rewritten by an alias, import map or protocol.
+  pub original_specifier: Option<JsWord>,
+  /// The names imported from the dependency, or `*` if all of them may be used.
+  pub imported_symbols: Vec<JsWord>,
+  /// Whether the dependency is only loaded for its side effects without binding any names,
+  /// e.g. `import './polyfill'` or `require('./register')`.
+  pub is_side_effect_only: bool,
+  /// The location of the specifier, or of the expression implying the dependency
+  /// if the specifier does not appear in the code (e.g. the `process` global).
+  pub specifier_loc: SourceLocation,
+  /// The location of the statement or declaration containing the dependency.
+  pub statement_loc: SourceLocation,
+  /// Byte offsets of the specifier, e.g. so editors can replace just the specifier.
+  /// For string literals, the range includes the quotes.
+  pub specifier_range: Option<ByteRange>,
+  /// Byte offsets of the statement or declaration containing the dependency.
+  pub statement_range: Option<ByteRange>,
+}
+
+impl DependencyDescriptor {
+  /// Creates a dependency whose specifier is at `span`, within the statement at `statement_span`.
+  /// Dependencies outside of a statement, e.g. helpers, use the specifier's location for both.
+  /// Other fields are set to their defaults, and can be overridden with struct update syntax.
+  pub fn new(
+    source_map: &SourceMap,
+    kind: DependencyKind,
+    specifier: JsWord,
+    span: Span,
+    statement_span: Span,
+  ) -> Self {
+    let statement_span = if span.is_dummy() || statement_span.is_dummy() {
+      span
+    } else {
+      statement_span
+    };
+
+    DependencyDescriptor {
+      kind,
+      loc: SourceLocation::from(source_map, span),
+      specifier_type: SpecifierType::from_specifier(&specifier),
+      specifier,
+      attributes: None,
+      is_optional: false,
+      is_helper: false,
+      source_type: None,
+      placeholder: None,
+      glob: None,
+      bundle_name: None,
+      priority: Priority::Sync,
+      resource_hint: None,
+      worklet_type: None,
+      is_external: false,
+      is_builtin: false,
+      original_specifier: None,
+      imported_symbols: vec![],
+      is_side_effect_only: false,
+      specifier_loc: SourceLocation::from(source_map, span),
+      statement_loc: SourceLocation::from(source_map, statement_span),
+      specifier_range: ByteRange::from(source_map, span),
+      statement_range: ByteRange::from(source_map, statement_span),
+    }
+  }
+}
+
+/// When a dependency is loaded relative to its parent.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+  Sync,
+  Parallel,
+  Lazy,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResourceHint {
+  Preload,
+  Prefetch,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkletType {
+  Paint,
+  Audio,
+  Animation,
+  Layout,
+}
+
+/// How a dependency specifier is interpreted.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SpecifierType {
+  /// A remote URL, e.g. `https://esm.sh/react`.
+  Url,
+  /// An inline `data:` URL.
+  Data,
+  /// A package referenced through a protocol, e.g. `npm:lodash` or `jsr:@std/path`.
+  PackageProtocol,
+  Relative,
+  Bare,
+  Absolute,
+}
+
+impl SpecifierType {
+  pub fn from_specifier(specifier: &str) -> Self {
+    if specifier.starts_with("http:")
+      || specifier.starts_with("https:")
+      || specifier.starts_with("//")
+    {
+      SpecifierType::Url
+    } else if specifier.starts_with("data:") {
+      SpecifierType::Data
+    } else if specifier.starts_with("npm:") || specifier.starts_with("jsr:") {
+      SpecifierType::PackageProtocol
+    } else if specifier == "."
+      || specifier == ".."
+      || specifier.starts_with("./")
+      || specifier.starts_with("../")
+    {
+      SpecifierType::Relative
+    } else if specifier.starts_with('/')
+      || specifier.starts_with("file:")
+      || Path::new(specifier).is_absolute()
+    {
+      SpecifierType::Absolute
+    } else {
+      SpecifierType::Bare
+    }
+  }
+}
+
+/// Options of an `import.meta.glob` or `require.context` call.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct GlobOptions {
+  /// Whether matched modules are bundled synchronously, or loaded lazily on demand.
+  pub eager: bool,
+  /// Only import this export from each matched module.
+  pub import: Option<JsWord>,
+  /// A query string appended to each matched module's specifier.
+  pub query: Option<JsWord>,
+  /// Negated patterns excluding matched files.
+  pub ignore: Vec<JsWord>,
+  /// A regular expression source and flags that relative paths must match (require.context).
+  pub filter: Option<(JsWord, JsWord)>,
+  /// Whether the result is a webpack-style context function rather than an object.
+  pub context: bool,
 }
 
 /// This pass collects dependencies in a module and compiles references as needed to work with Parcel's JSRuntime.
+#[allow(clippy::too_many_arguments)]
 pub fn dependency_collector<'a>(
   source_map: &'a SourceMap,
+  comments: &'a SingleThreadedComments,
   items: &'a mut Vec<DependencyDescriptor>,
   decls: &'a HashSet<Id>,
   ignore_mark: swc_common::Mark,
@@ -67,8 +236,11 @@ pub fn dependency_collector<'a>(
 ) -> impl Fold + 'a {
   DependencyCollector {
     source_map,
+    comments,
     items,
     in_try: false,
+    in_try_scope: false,
+    handles_rejection: false,
     in_promise: false,
     require_node: None,
     decls,
@@ -77,13 +249,35 @@ pub fn dependency_collector<'a>(
     config,
     diagnostics,
     import_meta: None,
+    node_builtins: NodeBuiltins::default(),
+    amd_require: HashSet::new(),
+    statement_span: DUMMY_SP,
   }
 }
 
+/// Options from magic comments preceding a dependency specifier.
+#[derive(Default)]
+struct MagicComments {
+  ignore: bool,
+  bundle_name: Option<JsWord>,
+  priority: Option<Priority>,
+  resource_hint: Option<ResourceHint>,
+}
+
+enum GlobCall {
+  ImportMeta { eager: bool },
+  RequireContext,
+}
+
 struct DependencyCollector<'a> {
   source_map: &'a SourceMap,
+  comments: &'a SingleThreadedComments,
   items: &'a mut Vec<DependencyDescriptor>,
   in_try: bool,
+  /// Whether awaits and returns in the current function are inside a try block.
+  in_try_scope: bool,
+  /// Whether a rejection of the next dynamic import is handled, e.g. by `.catch()`.
+  handles_rejection: bool,
   in_promise: bool,
   require_node: Option<ast::CallExpr>,
   decls: &'a HashSet<Id>,
@@ -92,6 +286,10 @@ struct DependencyCollector<'a> {
   config: &'a Config,
   diagnostics: &'a mut Vec<Diagnostic>,
   import_meta: Option<ast::VarDecl>,
+  node_builtins: NodeBuiltins,
+  amd_require: HashSet<Id>,
+  /// The statement being folded, for dependency locations.
+  statement_span: Span,
 }
 
 impl<'a> DependencyCollector<'a> {
@@ -100,7 +298,7 @@ impl<'a> DependencyCollector<'a> {
     specifier: JsWord,
     span: swc_common::Span,
     kind: DependencyKind,
-    attributes: Option<HashMap<swc_atoms::JsWord, bool>>,
+    attributes: Option<HashMap<swc_atoms::JsWord, swc_atoms::JsWord>>,
     is_optional: bool,
     source_type: SourceType,
   ) -> Option<JsWord> {
@@ -108,7 +306,12 @@ impl<'a> DependencyCollector<'a> {
     // For other types of dependencies, the specifier will be changed to a hash
     // that also contains the dependency kind. This way, multiple kinds of dependencies
     // to the same specifier can be used within the same file.
-    let placeholder = match kind {
+    let priority = match kind {
+      DependencyKind::DynamicImport => Priority::Lazy,
+      _ => Priority::Sync,
+    };
+
+    let mut placeholder = match kind {
       DependencyKind::Import | DependencyKind::Export | DependencyKind::Require => None,
       _ => Some(format!(
         "{:x}",
@@ -116,20 +319,88 @@ impl<'a> DependencyCollector<'a> {
       )),
     };
 
+    // Apply aliases and the import map, then map protocols to package specifiers,
+    // e.g. `jsr:@std/path`. The code keeps the original specifier, so use it as the
+    // placeholder if there isn't one already.
+    let original_specifier = specifier;
+    let mut specifier = original_specifier.clone();
+    if let Some(aliased) = self.resolve_alias(&specifier) {
+      specifier = aliased.into();
+    }
+    // The longest matching protocol wins, e.g. `npm:@scope/` over `npm:`.
+    if let Some((protocol, prefix)) = self
+      .config
+      .protocols
+      .iter()
+      .filter(|(protocol, _)| specifier.starts_with(protocol.as_str()))
+      .max_by_key(|(protocol, _)| protocol.len())
+    {
+      specifier = format!("{}{}", prefix, &specifier[protocol.len()..]).into();
+    }
+    let original_specifier = if specifier != original_specifier {
+      placeholder.get_or_insert_with(|| original_specifier.to_string());
+      Some(original_specifier)
+    } else {
+      None
+    };
+
+    let specifier_type = SpecifierType::from_specifier(&specifier);
+    let is_builtin = specifier.starts_with("node:");
+    let is_external = specifier_type == SpecifierType::Url && self.config.externalize_remote_urls;
+
+    // Externalized remote URLs are loaded with a native import, which other output formats
+    // would turn into an unsupported require. Dynamic imports are kept as import() calls.
+    if is_external
+      && !self.config.is_esm_output
+      && matches!(kind, DependencyKind::Import | DependencyKind::Export)
+    {
+      self.diagnostics.push(Diagnostic {
+        message: format!(
+          "Remote URL \"{}\" can only be imported in an ES module output format.",
+          specifier
+        ),
+        code_highlights: Some(vec![CodeHighlight {
+          message: None,
+          loc: SourceLocation::from(self.source_map, span),
+        }]),
+        hints: Some(vec![String::from(
+          "Use a dynamic import() to load this URL instead.",
+        )]),
+        show_environment: true,
+        severity: DiagnosticSeverity::Error,
+        documentation_url: None,
+      });
+    }
+
     self.items.push(DependencyDescriptor {
-      kind,
-      loc: SourceLocation::from(self.source_map, span),
-      specifier,
       attributes,
       is_optional,
       is_helper: span.is_dummy(),
       source_type: Some(source_type),
       placeholder: placeholder.clone(),
+      priority,
+      is_external,
+      is_builtin,
+      original_specifier,
+      ..DependencyDescriptor::new(self.source_map, kind, specifier, span, self.statement_span)
     });
 
     placeholder.map(|p| p.into())
   }
 
+  fn resolve_alias(&self, specifier: &str) -> Option<String> {
+    if let Some(aliased) = match_alias(&self.config.aliases, specifier) {
+      return Some(aliased);
+    }
+
+    let import_map = self.config.import_map.as_ref()?;
+    let referrer = match pathdiff::diff_paths(&self.config.filename, &self.config.project_root) {
+      Some(relative) => format!("/{}", relative.to_slash_lossy()),
+      None => self.config.filename.clone(),
+    };
+    import_map.resolve(specifier, &referrer)
+  }
+
   fn add_url_dependency(
     &mut self,
     specifier: JsWord,
@@ -160,14 +431,10 @@ impl<'a> DependencyCollector<'a> {
       ))
     );
     self.items.push(DependencyDescriptor {
-      kind,
-      loc: SourceLocation::from(self.source_map, span),
-      specifier,
-      attributes: None,
-      is_optional: false,
       is_helper: span.is_dummy(),
       source_type: Some(source_type),
       placeholder: Some(placeholder.clone()),
+      ..DependencyDescriptor::new(self.source_map, kind, specifier, span, self.statement_span)
     });
 
     create_url_constructor(
@@ -176,6 +443,553 @@ impl<'a> DependencyCollector<'a> {
     )
   }
 
+  /// Folds an import chain whose rejection is handled by the caller, marking the import
+  /// at its root as optional. The flag is cleared even if no dependency consumed it.
+  fn fold_handled_import(&mut self, expr: Box<ast::Expr>) -> Box<ast::Expr> {
+    self.handles_rejection = true;
+    let expr = expr.fold_with(self);
+    self.handles_rejection = false;
+    expr
+  }
+
+  /// Replaces a relative string passed to `fetch` in the first argument of a WebAssembly
+  /// streaming API with a URL dependency. `new URL(...)` arguments are handled by fold_expr.
+  /// Returns false if the argument was left unchanged.
+  fn fold_wasm_fetch(&mut self, args: &mut [ast::ExprOrSpread]) -> bool {
+    let fetch = match args.first_mut() {
+      Some(ast::ExprOrSpread { spread: None, expr }) => match &mut **expr {
+        ast::Expr::Call(call) => call,
+        _ => return false,
+      },
+      _ => return false,
+    };
+
+    match &fetch.callee {
+      Callee::Expr(callee) => match &**callee {
+        ast::Expr::Ident(ident)
+          if &*ident.sym == "fetch" && !self.decls.contains(&ident.to_id()) => {}
+        _ => return false,
+      },
+      _ => return false,
+    }
+
+    let (specifier, span) = match fetch.args.first() {
+      Some(ast::ExprOrSpread { spread: None, expr }) => match &**expr {
+        ast::Expr::Lit(ast::Lit::Str(str_))
+          if str_.value.starts_with("./") || str_.value.starts_with("../") =>
+        {
+          (str_.value.clone(), str_.span)
+        }
+        _ => return false,
+      },
+      _ => return false,
+    };
+
+    self.diagnostics.push(Diagnostic {
+      message: "Fetching a WebAssembly module with a string literal resolves it relative to the page rather than the module.".to_string(),
+      code_highlights: Some(vec![CodeHighlight {
+        message: None,
+        loc: SourceLocation::from(self.source_map, span),
+      }]),
+      hints: Some(vec![format!(
+        "Replace with: new URL('{}', import.meta.url)",
+        specifier
+      )]),
+      show_environment: false,
+      severity: DiagnosticSeverity::Warning,
+      documentation_url: None,
+    });
+
+    *fetch.args[0].expr = self.add_url_dependency(
+      specifier,
+      span,
+      DependencyKind::Url,
+      self.config.source_type,
+    );
+    let rest = fetch.args.split_off(1);
+    fetch.args.extend(rest.fold_with(self));
+    true
+  }
+
+  /// Matches `CSS.paintWorklet.addModule`, `CSS.animationWorklet.addModule`,
+  /// `CSS.layoutWorklet.addModule`, and `audioContext.audioWorklet.addModule`.
+  fn match_worklet(&self, member: &ast::MemberExpr) -> Option<WorkletType> {
+    if !self.config.context.is_browser() {
+      return None;
+    }
+
+    for (name, ty) in [
+      ("paintWorklet", WorkletType::Paint),
+      ("animationWorklet", WorkletType::Animation),
+      ("layoutWorklet", WorkletType::Layout),
+    ] {
+      if match_member_expr(member, vec!["CSS", name, "addModule"], self.decls) {
+        return Some(ty);
+      }
+    }
+
+    // Audio worklets belong to an AudioContext instance rather than a global.
+    match (match_property_name(member), &*member.obj) {
+      (Some((name, _)), ast::Expr::Member(obj))
+        if &*name == "addModule"
+          && matches!(match_property_name(obj), Some((name, _)) if &*name == "audioWorklet") =>
+      {
+        Some(WorkletType::Audio)
+      }
+      _ => None,
+    }
+  }
+
+  /// Replaces a `new URL(...)` entry point passed to a Node worker or child process
+  /// with a URL dependency. Returns false if the argument could not be statically analyzed.
+  fn fold_node_entry_arg(&mut self, args: &mut [ast::ExprOrSpread], kind: DependencyKind) -> bool {
+    let arg = match args.first_mut() {
+      Some(arg) => arg,
+      None => return false,
+    };
+
+    let (specifier, span) = match self.match_new_url(&arg.expr, self.decls) {
+      Some(s) => s,
+      None => return false,
+    };
+
+    let url = self.add_url_dependency(specifier, span, kind, SourceType::Module);
+
+    // The URL runtime returns a string, but Node treats strings as paths rather than file URLs.
+    *arg.expr = if self.config.is_library {
+      url
+    } else {
+      ast::Expr::New(ast::NewExpr {
+        span: DUMMY_SP,
+        callee: Box::new(ast::Expr::Ident(ast::Ident::new(js_word!("URL"), DUMMY_SP))),
+        args: Some(vec![ast::ExprOrSpread {
+          spread: None,
+          expr: Box::new(url),
+        }]),
+        type_args: None,
+      })
+    };
+    true
+  }
+
+  /// Matches `import.meta.glob`, `import.meta.globEager` and `require.context`.
+  fn match_glob_call(&mut self, member: &ast::MemberExpr) -> Option<GlobCall> {
+    if match_member_expr(member, vec!["require", "context"], self.decls) {
+      return Some(GlobCall::RequireContext);
+    }
+
+    let eager = match match_property_name(member) {
+      Some((name, _)) if &*name == "glob" => false,
+      Some((name, _)) if &*name == "globEager" => true,
+      _ => return None,
+    };
+
+    if self.is_import_meta(&member.obj) {
+      Some(GlobCall::ImportMeta { eager })
+    } else {
+      None
+    }
+  }
+
+  /// Adds a glob dependency for each pattern, and replaces the call with a
+  /// require of the map of matched modules that is generated when resolving it.
+  fn fold_glob_call(&mut self, node: ast::CallExpr, glob: GlobCall) -> ast::CallExpr {
+    let res = match glob {
+      GlobCall::ImportMeta { eager } => self.match_import_meta_glob(&node, eager),
+      GlobCall::RequireContext => self.match_require_context(&node),
+    };
+
+    let (patterns, opts, span) = match res {
+      Some(res) => res,
+      None => return node,
+    };
+
+    let mut requires: Vec<ast::CallExpr> = patterns
+      .into_iter()
+      .map(|pattern| {
+        let placeholder = self
+          .add_dependency(
+            pattern.clone(),
+            span,
+            DependencyKind::Glob,
+            None,
+            false,
+            self.config.source_type,
+          )
+          .unwrap_or(pattern);
+        if let Some(dep) = self.items.last_mut() {
+          dep.glob = Some(opts.clone());
+        }
+        self.create_require(placeholder)
+      })
+      .collect();
+
+    if requires.len() == 1 {
+      let mut call = requires.remove(0);
+      call.span = node.span;
+      return call;
+    }
+
+    // Merge the results of multiple patterns: Object.assign({}, require(a), require(b))
+    let mut args = vec![ast::ExprOrSpread {
+      spread: None,
+      expr: Box::new(ast::Expr::Object(ast::ObjectLit {
+        span: DUMMY_SP,
+        props: vec![],
+      })),
+    }];
+    args.extend(requires.into_iter().map(|call| ast::ExprOrSpread {
+      spread: None,
+      expr: Box::new(ast::Expr::Call(call)),
+    }));
+
+    ast::CallExpr {
+      span: node.span,
+      callee: ast::Callee::Expr(Box::new(ast::Expr::Member(ast::MemberExpr {
+        span: DUMMY_SP,
+        obj: Box::new(ast::Expr::Ident(ast::Ident::new("Object".into(), DUMMY_SP))),
+        prop: MemberProp::Ident(ast::Ident::new("assign".into(), DUMMY_SP)),
+      }))),
+      args,
+      type_args: None,
+    }
+  }
+
+  // import.meta.glob(pattern | [...patterns], {eager, import, query})
+  fn match_import_meta_glob(
+    &mut self,
+    node: &ast::CallExpr,
+    eager: bool,
+  ) -> Option<(Vec<JsWord>, GlobOptions, Span)> {
+    let mut opts = GlobOptions {
+      eager,
+      ..Default::default()
+    };
+
+    let mut patterns = vec![];
+    let arg = node.args.first();
+    let (values, span) = match arg.map(|arg| &*arg.expr) {
+      Some(ast::Expr::Array(array)) => (
+        array
+          .elems
+          .iter()
+          .map(|elem| match elem {
+            Some(ast::ExprOrSpread { spread: None, expr }) => match_str(expr),
+            _ => None,
+          })
+          .collect::<Option<Vec<_>>>(),
+        array.span,
+      ),
+      Some(expr) => (match_str(expr).map(|s| vec![s]), expr.span()),
+      None => (None, node.span),
+    };
+
+    let values = match values {
+      Some(values) if !values.is_empty() => values,
+      _ => {
+        self.add_glob_error(
+          "import.meta.glob() must be called with string literals.",
+          span,
+        );
+        return None;
+      }
+    };
+
+    for (value, _) in values {
+      match value.strip_prefix('!') {
+        Some(ignore) => opts.ignore.push(ignore.into()),
+        None => patterns.push(value),
+      }
+    }
+
+    if patterns.is_empty() {
+      self.add_glob_error("import.meta.glob() requires at least one pattern.", span);
+      return None;
+    }
+
+    if let Some(ast::Expr::Object(obj)) = node.args.get(1).map(|arg| &*arg.expr) {
+      for prop in &obj.props {
+        let kv = match prop {
+          ast::PropOrSpread::Prop(prop) => match &**prop {
+            ast::Prop::KeyValue(kv) => kv,
+            _ => continue,
+          },
+          _ => continue,
+        };
+
+        let key = match &kv.key {
+          ast::PropName::Ident(ast::Ident { sym, .. })
+          | ast::PropName::Str(ast::Str { value: sym, .. }) => sym,
+          _ => continue,
+        };
+
+        match (&**key, &*kv.value) {
+          ("eager", ast::Expr::Lit(ast::Lit::Bool(ast::Bool { value, .. }))) => opts.eager = *value,
+          ("import", value) => opts.import = match_str(value).map(|(s, _)| s),
+          ("query", value) => opts.query = match_str(value).map(|(s, _)| s),
+          _ => {}
+        }
+      }
+    }
+
+    Some((patterns, opts, span))
+  }
+
+  // require.context(directory, useSubdirectories = true, regExp = /^\.\/.*$/, mode = 'sync')
+  fn match_require_context(
+    &mut self,
+    node: &ast::CallExpr,
+  ) -> Option<(Vec<JsWord>, GlobOptions, Span)> {
+    let (directory, span) = match node.args.first().and_then(|arg| match_str(&arg.expr)) {
+      Some(res) => res,
+      None => {
+        self.add_glob_error(
+          "require.context() must be called with a string literal directory.",
+          node.span,
+        );
+        return None;
+      }
+    };
+
+    let recursive = match node.args.get(1).map(|arg| &*arg.expr) {
+      Some(ast::Expr::Lit(ast::Lit::Bool(ast::Bool { value, .. }))) => *value,
+      _ => true,
+    };
+
+    let filter = match node.args.get(2).map(|arg| &*arg.expr) {
+      Some(ast::Expr::Lit(ast::Lit::Regex(regex))) => {
+        Some((regex.exp.clone(), regex.flags.clone()))
+      }
+      _ => None,
+    };
+
+    let mode = node.args.get(3).and_then(|arg| match_str(&arg.expr));
+    let eager = match &mode {
+      Some((mode, _)) => &**mode == "sync" || &**mode == "weak",
+      None => true,
+    };
+
+    let pattern = format!(
+      "{}/{}",
+      directory.trim_end_matches('/'),
+      if recursive { "**/*" } else { "*" }
+    );
+
+    Some((
+      vec![pattern.into()],
+      GlobOptions {
+        eager,
+        filter,
+        context: true,
+        ..Default::default()
+      },
+      span,
+    ))
+  }
+
+  /// Matches template literals and string concatenations with a static prefix,
+  /// and converts them to a glob pattern where each expression is replaced with `*`.
+  fn match_partial_glob(&mut self, expr: &ast::Expr) -> Option<(JsWord, Span)> {
+    fn collect_parts(expr: &ast::Expr, parts: &mut Vec<Option<JsWord>>) {
+      match expr {
+        ast::Expr::Tpl(tpl) => {
+          for (i, quasi) in tpl.quasis.iter().enumerate() {
+            parts.push(Some(
+              quasi.cooked.clone().unwrap_or_else(|| quasi.raw.clone()),
+            ));
+            if i < tpl.exprs.len() {
+              parts.push(None);
+            }
+          }
+        }
+        ast::Expr::Bin(ast::BinExpr {
+          op: ast::BinaryOp::Add,
+          left,
+          right,
+          ..
+        }) => {
+          collect_parts(left, parts);
+          collect_parts(right, parts);
+        }
+        ast::Expr::Lit(ast::Lit::Str(s)) => parts.push(Some(s.value.clone())),
+        _ => parts.push(None),
+      }
+    }
+
+    let span = match expr {
+      ast::Expr::Tpl(tpl) => tpl.span,
+      ast::Expr::Bin(bin) if bin.op == ast::BinaryOp::Add => bin.span,
+      _ => return None,
+    };
+
+    let mut parts = vec![];
+    collect_parts(expr, &mut parts);
+
+    // Fully dynamic or fully static specifiers are handled elsewhere.
+    if !matches!(parts.first(), Some(Some(prefix)) if !prefix.is_empty())
+      || parts.iter().all(|part| part.is_some())
+    {
+      return None;
+    }
+
+    // Only paths can be globbed. Bare specifiers like import(`lodash/${name}`) are left as is.
+    let prefix: String = parts.iter().map_while(|part| part.as_deref()).collect();
+    if !prefix.starts_with("./") && !prefix.starts_with("../") && !prefix.starts_with('/') {
+      return None;
+    }
+
+    // Literal parts are escaped so characters like `[` in `./[id]/${name}.js` are not globs.
+    let mut pattern = String::new();
+    let mut is_wildcard = false;
+    for part in &parts {
+      match part {
+        Some(part) => {
+          pattern.push_str(&escape_glob(part));
+          is_wildcard = part.is_empty() && is_wildcard;
+        }
+        None if !is_wildcard => {
+          pattern.push('*');
+          is_wildcard = true;
+        }
+        None => {}
+      }
+    }
+
+    // Avoid bundling every file in a directory (including the importer itself),
+    // e.g. import(`./${name}`), import(`../${name}`).
+    let (dir, file) = pattern.rsplit_once('/').unwrap_or(("", &pattern));
+    let is_unconstrained = file == "*" && dir.split('/').all(|s| s == "." || s == "..");
+
+    if is_unconstrained {
+      self.diagnostics.push(Diagnostic {
+        message: format!(
+          "The dynamic import pattern \"{}\" is too broad to be bundled.",
+          pattern
+        ),
+        code_highlights: Some(vec![CodeHighlight {
+          message: None,
+          loc: SourceLocation::from(self.source_map, span),
+        }]),
+        hints: Some(vec![String::from(
+          "Add a static directory or file extension, e.g. import(`./locales/${name}.json`).",
+        )]),
+        show_environment: false,
+        severity: DiagnosticSeverity::Warning,
+        documentation_url: None,
+      });
+      return None;
+    }
+
+    Some((pattern.into(), span))
+  }
+
+  /// Replaces a dynamic import of a partial glob with a lookup in the map of matched modules.
+  /// import(`./locales/${lang}.json`)
+  ///   -> Promise.resolve().then(function () { return require('./locales/*.json')[`./locales/${lang}.json`](); })
+  fn fold_partial_glob_import(
+    &mut self,
+    node: ast::CallExpr,
+    pattern: JsWord,
+    span: Span,
+  ) -> ast::CallExpr {
+    let placeholder = self
+      .add_dependency(
+        pattern.clone(),
+        span,
+        DependencyKind::Glob,
+        None,
+        false,
+        self.config.source_type,
+      )
+      .unwrap_or(pattern);
+    if let Some(dep) = self.items.last_mut() {
+      dep.glob = Some(GlobOptions::default());
+    }
+
+    let key = node.args[0].expr.clone().fold_with(self);
+    let lookup = ast::CallExpr {
+      span: DUMMY_SP,
+      callee: ast::Callee::Expr(Box::new(ast::Expr::Member(ast::MemberExpr {
+        span: DUMMY_SP,
+        obj: Box::new(ast::Expr::Call(self.create_require(placeholder))),
+        prop: MemberProp::Computed(ast::ComputedPropName {
+          span: DUMMY_SP,
+          expr: key,
+        }),
+      }))),
+      args: vec![],
+      type_args: None,
+    };
+
+    create_promise_then(
+      vec![ast::Stmt::Return(ast::ReturnStmt {
+        span: DUMMY_SP,
+        arg: Some(Box::new(ast::Expr::Call(lookup))),
+      })],
+      node.span,
+    )
+  }
+
+  fn match_magic_comments(&self, pos: BytePos) -> MagicComments {
+    let mut magic_comments = MagicComments::default();
+    let comments = match self.comments.get_leading(pos) {
+      Some(comments) => comments,
+      None => return magic_comments,
+    };
+
+    for comment in comments {
+      for entry in comment.text.split(',') {
+        let (key, value) = match entry.split_once(':') {
+          Some((key, value)) => (key.trim(), value.trim()),
+          None => (entry.trim(), "true"),
+        };
+
+        match key {
+          "@parcel-ignore" | "parcelIgnore" | "webpackIgnore" => {
+            magic_comments.ignore = value == "true";
+          }
+          "parcelPreload" | "webpackPreload" if value != "false" => {
+            magic_comments.resource_hint = Some(ResourceHint::Preload);
+          }
+          "parcelPrefetch" | "webpackPrefetch" if value != "false" => {
+            magic_comments.resource_hint = Some(ResourceHint::Prefetch);
+          }
+          "parcelPriority" => {
+            magic_comments.priority = match value.trim_matches(|c| c == '"' || c == '\'') {
+              "sync" => Some(Priority::Sync),
+              "parallel" => Some(Priority::Parallel),
+              "lazy" => Some(Priority::Lazy),
+              _ => None,
+            };
+          }
+          "parcelChunkName" | "webpackChunkName" => {
+            let name = value.trim_matches(|c| c == '"' || c == '\'');
+            if !name.is_empty() {
+              magic_comments.bundle_name = Some(name.into());
+            }
+          }
+          _ => {}
+        }
+      }
+    }
+
+    magic_comments
+  }
+
+  fn add_glob_error(&mut self, message: &str, span: Span) {
+    self.diagnostics.push(Diagnostic {
+      message: message.to_string(),
+      code_highlights: Some(vec![CodeHighlight {
+        message: None,
+        loc: SourceLocation::from(self.source_map, span),
+      }]),
+      hints: None,
+      show_environment: false,
+      severity: DiagnosticSeverity::Error,
+      documentation_url: None,
+    });
+  }
+
   fn create_require(&mut self, specifier: JsWord) -> ast::CallExpr {
     let mut res = create_require(specifier);
 
@@ -190,6 +1004,22 @@ impl<'a> DependencyCollector<'a> {
     res
   }
 
+  /// Strips the `node:` prefix from a require call if the target doesn't support it.
+  fn rewrite_require_specifier(&self, mut node: ast::CallExpr) -> ast::CallExpr {
+    if let Some(arg) = node.args.first_mut() {
+      if let Some((value, span)) = match_str(&arg.expr) {
+        if value.starts_with("node:") {
+          *arg.expr = ast::Expr::Lit(ast::Lit::Str(ast::Str {
+            value: normalize_node_specifier(value, targets_to_versions(&self.config.targets)),
+            span,
+            raw: None,
+          }));
+        }
+      }
+    }
+    node
+  }
+
   fn add_script_error(&mut self, span: Span) {
     // Only add the diagnostic for imports/exports in scripts once.
     if self.diagnostics.iter().any(|d| d.message == "SCRIPT_ERROR") {
@@ -212,20 +1042,17 @@ impl<'a> DependencyCollector<'a> {
   }
 }
 
-fn rewrite_require_specifier(node: ast::CallExpr) -> ast::CallExpr {
-  if let Some(arg) = node.args.get(0) {
-    if let Some((value, _)) = match_str(&*arg.expr) {
-      if value.starts_with("node:") {
-        // create_require will take care of replacing the node: prefix...
-        return create_require(value);
-      }
-    }
-  }
-  node
-}
-
 impl<'a> Fold for DependencyCollector<'a> {
   fn fold_module(&mut self, node: ast::Module) -> ast::Module {
+    self.node_builtins = NodeBuiltins::collect(&node, self.decls, self.ignore_mark);
+    let mut node = node;
+    if !self.config.externals.is_empty() {
+      node.body = node
+        .body
+        .into_iter()
+        .flat_map(|item| self.fold_external_module_item(item))
+        .collect();
+    }
     let mut res = node.fold_children_with(self);
     if let Some(decl) = self.import_meta.take() {
       res.body.insert(
@@ -236,28 +1063,32 @@ impl<'a> Fold for DependencyCollector<'a> {
     res
   }
 
-  fn fold_module_decl(&mut self, node: ast::ModuleDecl) -> ast::ModuleDecl {
+  fold_statement_span! {}
+
+  fn fold_module_item(&mut self, node: ast::ModuleItem) -> ast::ModuleItem {
     // If an import or export is seen within a script, flag it to throw an error from JS.
     if self.config.source_type == SourceType::Script {
-      match node {
-        ast::ModuleDecl::Import(ast::ImportDecl { span, .. })
-        | ast::ModuleDecl::ExportAll(ast::ExportAll { span, .. })
-        | ast::ModuleDecl::ExportDecl(ast::ExportDecl { span, .. })
-        | ast::ModuleDecl::ExportDefaultDecl(ast::ExportDefaultDecl { span, .. })
-        | ast::ModuleDecl::ExportDefaultExpr(ast::ExportDefaultExpr { span, .. })
-        | ast::ModuleDecl::ExportNamed(ast::NamedExport { span, .. }) => {
-          self.add_script_error(span)
+      if let ast::ModuleItem::ModuleDecl(decl) = &node {
+        match decl {
+          ast::ModuleDecl::Import(ast::ImportDecl { span, .. })
+          | ast::ModuleDecl::ExportAll(ast::ExportAll { span, .. })
+          | ast::ModuleDecl::ExportDecl(ast::ExportDecl { span, .. })
+          | ast::ModuleDecl::ExportDefaultDecl(ast::ExportDefaultDecl { span, .. })
+          | ast::ModuleDecl::ExportDefaultExpr(ast::ExportDefaultExpr { span, .. })
+          | ast::ModuleDecl::ExportNamed(ast::NamedExport { span, .. }) => {
+            self.add_script_error(*span)
+          }
+          _ => {}
         }
-        _ => {}
+        return node;
       }
-      return node;
     }
 
     node.fold_children_with(self)
   }
 
   fn fold_import_decl(&mut self, node: ast::ImportDecl) -> ast::ImportDecl {
-    if node.type_only {
+    if node.type_only || self.external(&node.src.value).is_some() {
       return node;
     }
 
@@ -265,7 +1096,7 @@ impl<'a> Fold for DependencyCollector<'a> {
       node.src.value.clone(),
       node.src.span,
       DependencyKind::Import,
-      None,
+      node.asserts.as_ref().map(match_import_attributes),
       false,
       self.config.source_type,
     );
@@ -275,7 +1106,7 @@ impl<'a> Fold for DependencyCollector<'a> {
 
   fn fold_named_export(&mut self, node: ast::NamedExport) -> ast::NamedExport {
     if let Some(src) = &node.src {
-      if node.type_only {
+      if node.type_only || self.external(&src.value).is_some() {
         return node;
       }
 
@@ -283,7 +1114,7 @@ impl<'a> Fold for DependencyCollector<'a> {
         src.value.clone(),
         src.span,
         DependencyKind::Export,
-        None,
+        node.asserts.as_ref().map(match_import_attributes),
         false,
         self.config.source_type,
       );
@@ -293,11 +1124,15 @@ impl<'a> Fold for DependencyCollector<'a> {
   }
 
   fn fold_export_all(&mut self, node: ast::ExportAll) -> ast::ExportAll {
+    if self.external(&node.src.value).is_some() {
+      return node;
+    }
+
     self.add_dependency(
       node.src.value.clone(),
       node.src.span,
       DependencyKind::Export,
-      None,
+      node.asserts.as_ref().map(match_import_attributes),
       false,
       self.config.source_type,
     );
@@ -308,7 +1143,9 @@ impl<'a> Fold for DependencyCollector<'a> {
   fn fold_try_stmt(&mut self, node: ast::TryStmt) -> ast::TryStmt {
     // Track if we're inside a try block to mark dependencies as optional.
     self.in_try = true;
+    let in_try_scope = std::mem::replace(&mut self.in_try_scope, true);
     let block = node.block.fold_with(self);
+    self.in_try_scope = in_try_scope;
     self.in_try = false;
 
     let handler = node.handler.map(|handler| handler.fold_with(self));
@@ -322,9 +1159,110 @@ impl<'a> Fold for DependencyCollector<'a> {
     }
   }
 
+  fn fold_function(&mut self, node: ast::Function) -> ast::Function {
+    // A try block around a function doesn't catch rejections from awaits inside it,
+    // e.g. `try { setTimeout(async () => await import('foo')) } catch {}`.
+    let in_try_scope = std::mem::replace(&mut self.in_try_scope, false);
+    let node = node.fold_children_with(self);
+    self.in_try_scope = in_try_scope;
+    node
+  }
+
+  fn fold_arrow_expr(&mut self, node: ast::ArrowExpr) -> ast::ArrowExpr {
+    let in_try_scope = std::mem::replace(&mut self.in_try_scope, false);
+    let node = node.fold_children_with(self);
+    self.in_try_scope = in_try_scope;
+    node
+  }
+
+  fn fold_await_expr(&mut self, node: ast::AwaitExpr) -> ast::AwaitExpr {
+    // try { await import('foo') } catch {}
+    if self.in_try_scope && is_import_chain(&node.arg) {
+      return ast::AwaitExpr {
+        arg: self.fold_handled_import(node.arg),
+        ..node
+      };
+    }
+
+    node.fold_children_with(self)
+  }
+
+  fn fold_return_stmt(&mut self, node: ast::ReturnStmt) -> ast::ReturnStmt {
+    // try { return import('foo') } catch {}
+    match node.arg {
+      Some(arg) if self.in_try_scope && is_import_chain(&arg) => ast::ReturnStmt {
+        arg: Some(self.fold_handled_import(arg)),
+        ..node
+      },
+      arg => ast::ReturnStmt {
+        arg: arg.fold_with(self),
+        ..node
+      },
+    }
+  }
+
   fn fold_call_expr(&mut self, node: ast::CallExpr) -> ast::CallExpr {
     use ast::{Expr::*, Ident};
 
+    // Calls through a function returned by createRequire(import.meta.url), or the `require`
+    // parameter of an AMD factory, behave like `require`.
+    // Swap the callee for the global require so it is handled like any other require call.
+    if let Callee::Expr(expr) = &node.callee {
+      if let Ident(ident) = &**expr {
+        if self.node_builtins.create_require.contains(&ident.to_id())
+          || self.amd_require.contains(&ident.to_id())
+        {
+          let mut node = node;
+          node.callee = Callee::Expr(Box::new(Ident(Ident::new("require".into(), DUMMY_SP))));
+          return self.fold_call_expr(node);
+        }
+      }
+    }
+
+    // import.meta.glob('./pages/*.js'), require.context('./icons', true, /\.svg$/)
+    if let Callee::Expr(expr) = &node.callee {
+      if let Member(member) = &**expr {
+        if let Some(glob) = self.match_glob_call(member) {
+          return self.fold_glob_call(node, glob);
+        }
+      }
+    }
+
+    // WebAssembly.instantiateStreaming(fetch('./mod.wasm'))
+    if let Callee::Expr(expr) = &node.callee {
+      if let Member(member) = &**expr {
+        if match_member_expr(
+          member,
+          vec!["WebAssembly", "instantiateStreaming"],
+          self.decls,
+        ) || match_member_expr(member, vec!["WebAssembly", "compileStreaming"], self.decls)
+        {
+          let mut node = node;
+          if self.fold_wasm_fetch(&mut node.args) {
+            let rest = node.args.split_off(1);
+            node.args.extend(rest.fold_with(self));
+            return node;
+          }
+          return node.fold_children_with(self);
+        }
+      }
+    }
+
+    // child_process.fork(new URL('./child.js', import.meta.url))
+    if let Callee::Expr(expr) = &node.callee {
+      if self.config.context.is_node() && self.node_builtins.matches(expr, "child_process", "fork")
+      {
+        let mut node = node;
+        if self.fold_node_entry_arg(&mut node.args, DependencyKind::ChildProcess) {
+          let rest = node.args.split_off(1);
+          node.args.extend(rest.fold_with(self));
+          return node;
+        }
+        return node.fold_children_with(self);
+      }
+    }
+
+    let mut worklet_type = None;
     let kind = match &node.callee {
       Callee::Import(_) => DependencyKind::DynamicImport,
       Callee::Expr(expr) => {
@@ -337,61 +1275,103 @@ impl<'a> Fold for DependencyCollector<'a> {
 
             match ident.sym.to_string().as_str() {
               "require" => {
+                // AMD: require(['a', 'b'], function (a, b) { ... })
+                if self.config.source_type != SourceType::Script
+                  && matches!(node.args.first(), Some(ast::ExprOrSpread { spread: None, expr }) if matches!(&**expr, Array(_)))
+                {
+                  return self.fold_amd_require(node);
+                }
+
                 if self.in_promise {
                   DependencyKind::DynamicImport
                 } else {
                   DependencyKind::Require
                 }
               }
+              // AMD: define(['a', 'b'], function (a, b) { ... })
+              "define" if self.config.source_type != SourceType::Script => {
+                return self.fold_amd_define(node);
+              }
               "importScripts" => {
-                if self.config.is_worker {
+                let mut node = node;
+                if self.config.context.is_worker() {
                   let (msg, span) = if self.config.source_type == SourceType::Script {
-                    // Ignore if argument is not a string literal.
-                    let span = if let Some(ast::ExprOrSpread { expr, .. }) = node.args.get(0) {
-                      match &**expr {
-                        Lit(ast::Lit::Str(ast::Str { value, span, .. })) => {
-                          // Ignore absolute URLs.
-                          if value.starts_with("http:")
-                            || value.starts_with("https:")
-                            || value.starts_with("//")
+                    // importScripts(new URL('./a.js', import.meta.url), new URL('./b.js', import.meta.url))
+                    let mut string_span = None;
+                    let mut args = Vec::with_capacity(node.args.len());
+                    for arg in node.args.drain(..) {
+                      if arg.spread.is_none() {
+                        if let Some((specifier, span)) = match_script_url(&arg.expr, self.decls) {
+                          let url = self.add_url_dependency(
+                            specifier,
+                            span,
+                            DependencyKind::ImportScripts,
+                            SourceType::Script,
+                          );
+                          args.push(ast::ExprOrSpread {
+                            spread: None,
+                            expr: Box::new(url),
+                          });
+                          continue;
+                        }
+
+                        // Ignore absolute URLs and arguments that are not string literals.
+                        if let Lit(ast::Lit::Str(ast::Str { value, span, .. })) = &*arg.expr {
+                          if !value.starts_with("http:")
+                            && !value.starts_with("https:")
+                            && !value.starts_with("//")
                           {
-                            return node.fold_children_with(self);
+                            string_span.get_or_insert(*span);
                           }
-                          span
-                        }
-                        _ => {
-                          return node.fold_children_with(self);
                         }
                       }
-                    } else {
-                      return node.fold_children_with(self);
-                    };
-
-                    (
-                      "Argument to importScripts() must be a fully qualified URL.",
-                      *span,
-                    )
+                      args.push(arg.fold_with(self));
+                    }
+                    node.args = args;
+
+                    match string_span {
+                      Some(span) => (
+                        "Argument to importScripts() must be a fully qualified URL.",
+                        span,
+                      ),
+                      None => return node,
+                    }
                   } else {
                     (
                       "importScripts() is not supported in module workers.",
                       node.span,
                     )
                   };
+                  let hints = if self.config.source_type == SourceType::Script {
+                    vec![
+                      String::from("Use a static `import`, or dynamic `import()` instead."),
+                      String::from(
+                        "Or pass a URL to bundle the script: importScripts(new URL('./script.js', import.meta.url))",
+                      ),
+                    ]
+                  } else {
+                    vec![String::from(
+                      "Use a static `import`, or dynamic `import()` instead.",
+                    )]
+                  };
                   self.diagnostics.push(Diagnostic {
                     message: msg.to_string(),
                     code_highlights: Some(vec![CodeHighlight {
                       message: None,
                       loc: SourceLocation::from(self.source_map, span),
                     }]),
-                    hints: Some(vec![String::from(
-                      "Use a static `import`, or dynamic `import()` instead.",
-                    )]),
+                    hints: Some(hints),
                     show_environment: self.config.source_type == SourceType::Script,
                     severity: DiagnosticSeverity::Error,
                     documentation_url: Some(String::from(
                       "https://parceljs.org/languages/javascript/#classic-script-workers",
                     )),
                   });
+
+                  // The arguments of classic worker calls were already folded above.
+                  if self.config.source_type == SourceType::Script {
+                    return node;
+                  }
                 }
 
                 return node.fold_children_with(self);
@@ -426,7 +1406,7 @@ impl<'a> Fold for DependencyCollector<'a> {
           Member(member) => {
             if match_member_expr(member, vec!["module", "require"], self.decls) {
               DependencyKind::Require
-            } else if self.config.is_browser
+            } else if self.config.context.is_browser()
               && match_member_expr(
                 member,
                 vec!["navigator", "serviceWorker", "register"],
@@ -434,11 +1414,27 @@ impl<'a> Fold for DependencyCollector<'a> {
               )
             {
               DependencyKind::ServiceWorker
-            } else if self.config.is_browser
-              && match_member_expr(member, vec!["CSS", "paintWorklet", "addModule"], self.decls)
-            {
+            } else if let Some(ty) = self.match_worklet(member) {
+              worklet_type = Some(ty);
               DependencyKind::Worklet
             } else {
+              // import('foo').catch(...), import('foo').then(...).catch(...)
+              // A rejected import is handled at runtime, so resolution failures shouldn't fail the build.
+              if matches!(match_property_name(member), Some((name, _)) if &*name == "catch")
+                && is_import_chain(&member.obj)
+              {
+                // Only the import at the root of the callee chain is optional. Clear the flag once
+                // the callee is folded, even if no dependency consumed it (e.g. `import(foo)`).
+                self.handles_rejection = true;
+                let callee = node.callee.fold_with(self);
+                self.handles_rejection = false;
+                return ast::CallExpr {
+                  callee,
+                  args: node.args.fold_with(self),
+                  ..node
+                };
+              }
+
               let was_in_promise = self.in_promise;
 
               // Match compiled dynamic imports (Parcel)
@@ -498,38 +1494,68 @@ impl<'a> Fold for DependencyCollector<'a> {
       _ => return node.fold_children_with(self),
     };
 
-    // Convert import attributes for dynamic import
+    // Magic comments before the specifier, e.g. import(/* @parcel-ignore */ './foo')
+    let magic_comments = match node.args.first() {
+      Some(arg) => self.match_magic_comments(arg.expr.span().lo),
+      None => MagicComments::default(),
+    };
+
+    // Remote URLs are loaded natively when externalized, e.g. import('https://esm.sh/react')
+    let remote_import = match node.args.first() {
+      Some(arg) if kind == DependencyKind::DynamicImport && self.config.externalize_remote_urls => {
+        match_str(&arg.expr)
+          .filter(|(specifier, _)| SpecifierType::from_specifier(specifier) == SpecifierType::Url)
+      }
+      _ => None,
+    };
+
+    // Like static imports, the external dependency is still recorded. The code keeps
+    // the URL rather than a placeholder.
+    if let Some((specifier, span)) = remote_import.clone() {
+      let attributes = match node.args.get(1).map(|arg| &*arg.expr) {
+        Some(Object(arg)) => match_import_options(arg).0,
+        _ => None,
+      };
+      self.add_dependency(
+        specifier,
+        span,
+        kind.clone(),
+        attributes,
+        false,
+        self.config.source_type,
+      );
+      if let Some(dep) = self.items.last_mut() {
+        dep.placeholder = None;
+      }
+    }
+
+    // Leave ignored dependencies to be loaded at runtime.
+    if (magic_comments.ignore
+      && matches!(
+        kind,
+        DependencyKind::DynamicImport | DependencyKind::Require
+      ))
+      || remote_import.is_some()
+    {
+      let name = match node.callee {
+        Callee::Import(_) => "import",
+        _ => "require",
+      };
+      let mut call = node.fold_children_with(self);
+      call.callee = ast::Callee::Expr(Box::new(ast::Expr::Ident(ast::Ident::new(
+        name.into(),
+        DUMMY_SP.apply_mark(self.ignore_mark),
+      ))));
+      return call;
+    }
+
+    // Convert import attributes and options for dynamic import
     let mut attributes = None;
+    let mut attribute_hint = None;
     if kind == DependencyKind::DynamicImport {
       if let Some(arg) = node.args.get(1) {
         if let Object(arg) = &*arg.expr {
-          let mut attrs = HashMap::new();
-          for key in &arg.props {
-            let prop = match key {
-              ast::PropOrSpread::Prop(prop) => prop,
-              _ => continue,
-            };
-
-            let kv = match &**prop {
-              ast::Prop::KeyValue(kv) => kv,
-              _ => continue,
-            };
-
-            let k = match &kv.key {
-              ast::PropName::Ident(Ident { sym, .. })
-              | ast::PropName::Str(ast::Str { value: sym, .. }) => sym.clone(),
-              _ => continue,
-            };
-
-            let v = match &*kv.value {
-              Lit(ast::Lit::Bool(ast::Bool { value, .. })) => *value,
-              _ => continue,
-            };
-
-            attrs.insert(k, v);
-          }
-
-          attributes = Some(attrs);
+          (attributes, attribute_hint) = match_import_options(arg);
         }
       }
     }
@@ -578,6 +1604,9 @@ impl<'a> Fold for DependencyCollector<'a> {
         };
 
         node.args[0].expr = Box::new(self.add_url_dependency(specifier, span, kind, source_type));
+        if let Some(dep) = self.items.last_mut() {
+          dep.worklet_type = worklet_type;
+        }
 
         match opts {
           Some(opts) => {
@@ -590,6 +1619,13 @@ impl<'a> Fold for DependencyCollector<'a> {
         return node;
       }
 
+      // import(`./locales/${lang}.json`) -> lookup in a glob of ./locales/*.json
+      if kind == DependencyKind::DynamicImport {
+        if let Some((pattern, span)) = self.match_partial_glob(&arg.expr) {
+          return self.fold_partial_glob_import(node, pattern, span);
+        }
+      }
+
       if let Some((specifier, span)) = match_str(&*arg.expr) {
         // require() calls aren't allowed in scripts, flag as an error.
         if kind == DependencyKind::Require && self.config.source_type == SourceType::Script {
@@ -597,14 +1633,29 @@ impl<'a> Fold for DependencyCollector<'a> {
           return node;
         }
 
+        let is_optional = match kind {
+          DependencyKind::Require => self.in_try,
+          DependencyKind::DynamicImport => std::mem::take(&mut self.handles_rejection),
+          _ => false,
+        };
+
         let placeholder = self.add_dependency(
           specifier,
           span,
           kind.clone(),
           attributes,
-          kind == DependencyKind::Require && self.in_try,
+          is_optional,
           self.config.source_type,
         );
+        if let Some(dep) = self.items.last_mut() {
+          dep.bundle_name = magic_comments.bundle_name;
+          if kind == DependencyKind::DynamicImport {
+            if let Some(priority) = magic_comments.priority {
+              dep.priority = priority;
+            }
+            dep.resource_hint = magic_comments.resource_hint.or(attribute_hint);
+          }
+        }
 
         if let Some(placeholder) = placeholder {
           let mut node = node.clone();
@@ -642,12 +1693,12 @@ impl<'a> Fold for DependencyCollector<'a> {
       call.args.truncate(1);
 
       // Track the returned require call to be replaced with a promise chain.
-      let rewritten_call = rewrite_require_specifier(call);
+      let rewritten_call = self.rewrite_require_specifier(call);
       self.require_node = Some(rewritten_call.clone());
       rewritten_call
     } else if kind == DependencyKind::Require {
       // Don't continue traversing so that the `require` isn't replaced with undefined
-      rewrite_require_specifier(node)
+      self.rewrite_require_specifier(node)
     } else {
       node.fold_children_with(self)
     }
@@ -674,12 +1725,29 @@ impl<'a> Fold for DependencyCollector<'a> {
   fn fold_new_expr(&mut self, node: ast::NewExpr) -> ast::NewExpr {
     use ast::Expr::*;
 
+    // new Worker(new URL('./worker.js', import.meta.url)) from worker_threads
+    if self.config.context.is_node()
+      && self
+        .node_builtins
+        .matches(&node.callee, "worker_threads", "Worker")
+    {
+      let mut node = node;
+      if let Some(args) = &mut node.args {
+        if self.fold_node_entry_arg(args, DependencyKind::NodeWorker) {
+          let rest = args.split_off(1);
+          args.extend(rest.fold_with(self));
+          return node;
+        }
+      }
+      return node.fold_children_with(self);
+    }
+
     let matched = match &*node.callee {
       Ident(id) => {
         match &id.sym {
           &js_word!("Worker") | &js_word!("SharedWorker") => {
             // Bail if defined in scope
-            self.config.is_browser && !self.decls.contains(&id.to_id())
+            self.config.context.supports_web_workers() && !self.decls.contains(&id.to_id())
           }
           &js_word!("Promise") => {
             // Match requires inside promises (e.g. Rollup compiled dynamic imports)
@@ -789,6 +1857,38 @@ impl<'a> Fold for DependencyCollector<'a> {
       return self.get_import_meta_url();
     }
 
+    // require('react'), import('react')
+    if let Expr::Call(call) = &node {
+      if let Some(expr) = self.fold_external_call(call) {
+        return expr;
+      }
+    }
+
+    // require.resolve('./worker.js'), import.meta.resolve('./asset.wasm')
+    if let Expr::Call(call) = &node {
+      if let Some((specifier, span, is_require)) = self.match_resolve_call(call) {
+        // In Node, bare specifiers like require.resolve('lodash/package.json') resolve
+        // against node_modules at runtime, so only relative paths become URL dependencies.
+        let is_relative = specifier.starts_with('.') || specifier.starts_with('/');
+        if !is_require || !self.config.context.is_node() || is_relative {
+          return self.fold_resolve_call(specifier, span, call.span, is_require);
+        }
+
+        return Expr::Call(ast::CallExpr {
+          callee: ast::Callee::Expr(Box::new(Expr::Member(ast::MemberExpr {
+            span: DUMMY_SP,
+            obj: Box::new(Expr::Ident(Ident::new(
+              js_word!("require"),
+              DUMMY_SP.apply_mark(self.ignore_mark),
+            ))),
+            prop: MemberProp::Ident(Ident::new("resolve".into(), DUMMY_SP)),
+          }))),
+          args: call.args.clone().fold_with(self),
+          ..call.clone()
+        });
+      }
+    }
+
     if let Some((specifier, span)) = self.match_new_url(&node, self.decls) {
       let url = self.add_url_dependency(
         specifier,
@@ -1005,6 +2105,52 @@ fn build_promise_chain(node: ast::CallExpr, require_node: ast::CallExpr) -> ast:
   node
 }
 
+/// Creates `Promise.resolve().then(function () { ... })`.
+fn create_promise_then(stmts: Vec<ast::Stmt>, span: Span) -> ast::CallExpr {
+  let resolve = ast::CallExpr {
+    span: DUMMY_SP,
+    callee: ast::Callee::Expr(Box::new(ast::Expr::Member(ast::MemberExpr {
+      span: DUMMY_SP,
+      obj: Box::new(ast::Expr::Ident(ast::Ident::new(
+        "Promise".into(),
+        DUMMY_SP,
+      ))),
+      prop: MemberProp::Ident(ast::Ident::new("resolve".into(), DUMMY_SP)),
+    }))),
+    args: vec![],
+    type_args: None,
+  };
+
+  ast::CallExpr {
+    span,
+    callee: ast::Callee::Expr(Box::new(ast::Expr::Member(ast::MemberExpr {
+      span: DUMMY_SP,
+      obj: Box::new(ast::Expr::Call(resolve)),
+      prop: MemberProp::Ident(ast::Ident::new("then".into(), DUMMY_SP)),
+    }))),
+    args: vec![ast::ExprOrSpread {
+      spread: None,
+      expr: Box::new(ast::Expr::Fn(ast::FnExpr {
+        ident: None,
+        function: ast::Function {
+          params: vec![],
+          decorators: vec![],
+          span: DUMMY_SP,
+          body: Some(ast::BlockStmt {
+            span: DUMMY_SP,
+            stmts,
+          }),
+          is_generator: false,
+          is_async: false,
+          type_params: None,
+          return_type: None,
+        },
+      })),
+    }],
+    type_args: None,
+  }
+}
+
 fn create_url_constructor(url: ast::Expr, use_import_meta: bool) -> ast::Expr {
   use ast::*;
 
@@ -1075,26 +2221,664 @@ impl Fold for PromiseTransformer {
       }
     }
 
-    swc_ecmascript::visit::fold_arrow_expr(self, node)
-  }
-
-  fn fold_expr(&mut self, node: ast::Expr) -> ast::Expr {
-    let node = swc_ecmascript::visit::fold_expr(self, node);
+    swc_ecmascript::visit::fold_arrow_expr(self, node)
+  }
+
+  fn fold_expr(&mut self, node: ast::Expr) -> ast::Expr {
+    let node = swc_ecmascript::visit::fold_expr(self, node);
+
+    // Replace the original require node with a reference to a variable `res`,
+    // which will be added as a parameter to the parent function.
+    if let ast::Expr::Call(call) = &node {
+      if let Some(require_node) = &self.require_node {
+        if require_node == call {
+          return ast::Expr::Ident(ast::Ident::new("res".into(), DUMMY_SP));
+        }
+      }
+    }
+
+    node
+  }
+}
+
+impl<'a> DependencyCollector<'a> {
+  /// Compiles an AMD module definition to CommonJS.
+  ///
+  /// define(['a', 'exports'], function (a, exports) { ... })
+  ///   => (function (result) { if (result !== undefined) module.exports = result; })(
+  ///        (function (a, exports) { ... })(require('a'), exports))
+  fn fold_amd_define(&mut self, node: ast::CallExpr) -> ast::CallExpr {
+    let mut args = node
+      .args
+      .into_iter()
+      .filter(|arg| arg.spread.is_none())
+      .peekable();
+
+    // Skip the module id in named definitions.
+    if matches!(args.peek(), Some(arg) if matches!(&*arg.expr, ast::Expr::Lit(ast::Lit::Str(_)))) {
+      args.next();
+    }
+
+    let deps = match args.peek() {
+      Some(arg) if matches!(&*arg.expr, ast::Expr::Array(_)) => args.next(),
+      _ => None,
+    };
+    let factory = args.next().map(|arg| arg.expr);
+
+    let value = match factory {
+      Some(factory)
+        if matches!(
+          &*factory,
+          ast::Expr::Fn(_) | ast::Expr::Arrow(_) | ast::Expr::Ident(_)
+        ) =>
+      {
+        let params = amd_factory_params(&factory);
+        let args = if let Some(deps) = deps {
+          match *deps.expr {
+            ast::Expr::Array(deps) => self.fold_amd_deps(deps, &params),
+            _ => unreachable!(),
+          }
+        } else {
+          // Simplified CommonJS wrapper: define(function (require, exports, module) { ... })
+          if let Some(Some(id)) = params.first() {
+            self.amd_require.insert(id.clone());
+          }
+          ["require", "exports", "module"]
+            .iter()
+            .map(|name| ast::ExprOrSpread {
+              spread: None,
+              expr: Box::new(ast::Expr::Ident(ast::Ident::new((*name).into(), DUMMY_SP))),
+            })
+            .collect()
+        };
+
+        let factory = factory.fold_with(self);
+        match *factory {
+          // define(['a'], factory) may also pass an object or value by reference:
+          // typeof factory === 'function' ? factory(a) : factory
+          ast::Expr::Ident(id) => ast::Expr::Cond(ast::CondExpr {
+            span: DUMMY_SP,
+            test: Box::new(ast::Expr::Bin(ast::BinExpr {
+              span: DUMMY_SP,
+              op: ast::BinaryOp::EqEqEq,
+              left: Box::new(ast::Expr::Unary(ast::UnaryExpr {
+                span: DUMMY_SP,
+                op: ast::UnaryOp::TypeOf,
+                arg: Box::new(ast::Expr::Ident(id.clone())),
+              })),
+              right: Box::new(ast::Expr::Lit(ast::Lit::Str("function".into()))),
+            })),
+            cons: Box::new(ast::Expr::Call(ast::CallExpr {
+              span: DUMMY_SP,
+              callee: ast::Callee::Expr(Box::new(ast::Expr::Ident(id.clone()))),
+              args,
+              type_args: None,
+            })),
+            alt: Box::new(ast::Expr::Ident(id)),
+          }),
+          factory => ast::Expr::Call(ast::CallExpr {
+            span: DUMMY_SP,
+            callee: ast::Callee::Expr(Box::new(ast::Expr::Paren(ast::ParenExpr {
+              span: DUMMY_SP,
+              expr: Box::new(factory),
+            }))),
+            args,
+            type_args: None,
+          }),
+        }
+      }
+      // define({ ... })
+      Some(factory) => {
+        if let Some(deps) = deps {
+          if let ast::Expr::Array(deps) = *deps.expr {
+            self.fold_amd_deps(deps, &[]);
+          }
+        }
+        *factory.fold_with(self)
+      }
+      None => {
+        if let Some(deps) = deps {
+          if let ast::Expr::Array(deps) = *deps.expr {
+            self.fold_amd_deps(deps, &[]);
+          }
+        }
+        ast::Expr::Ident(ast::Ident::new(js_word!("undefined"), DUMMY_SP))
+      }
+    };
+
+    let result = ast::Ident::new("result".into(), DUMMY_SP);
+    let module_exports = ast::Expr::Member(ast::MemberExpr {
+      span: DUMMY_SP,
+      obj: Box::new(ast::Expr::Ident(ast::Ident::new(
+        js_word!("module"),
+        DUMMY_SP,
+      ))),
+      prop: MemberProp::Ident(ast::Ident::new("exports".into(), DUMMY_SP)),
+    });
+    let assign = ast::Stmt::If(ast::IfStmt {
+      span: DUMMY_SP,
+      test: Box::new(ast::Expr::Bin(ast::BinExpr {
+        span: DUMMY_SP,
+        op: ast::BinaryOp::NotEqEq,
+        left: Box::new(ast::Expr::Ident(result.clone())),
+        right: Box::new(ast::Expr::Ident(ast::Ident::new(
+          js_word!("undefined"),
+          DUMMY_SP,
+        ))),
+      })),
+      cons: Box::new(ast::Stmt::Expr(ast::ExprStmt {
+        span: DUMMY_SP,
+        expr: Box::new(ast::Expr::Assign(ast::AssignExpr {
+          span: DUMMY_SP,
+          op: ast::AssignOp::Assign,
+          left: ast::PatOrExpr::Expr(Box::new(module_exports)),
+          right: Box::new(ast::Expr::Ident(result.clone())),
+        })),
+      })),
+      alt: None,
+    });
+
+    ast::CallExpr {
+      span: node.span,
+      callee: ast::Callee::Expr(Box::new(ast::Expr::Paren(ast::ParenExpr {
+        span: DUMMY_SP,
+        expr: Box::new(ast::Expr::Fn(ast::FnExpr {
+          ident: None,
+          function: ast::Function {
+            params: vec![ast::Param {
+              span: DUMMY_SP,
+              decorators: vec![],
+              pat: ast::Pat::Ident(ast::BindingIdent::from(result)),
+            }],
+            decorators: vec![],
+            span: DUMMY_SP,
+            body: Some(ast::BlockStmt {
+              span: DUMMY_SP,
+              stmts: vec![assign],
+            }),
+            is_generator: false,
+            is_async: false,
+            type_params: None,
+            return_type: None,
+          },
+        })),
+      }))),
+      args: vec![ast::ExprOrSpread {
+        spread: None,
+        expr: Box::new(value),
+      }],
+      type_args: None,
+    }
+  }
+
+  /// Compiles an AMD require to CommonJS. The dependencies are bundled synchronously,
+  /// but the callback still runs asynchronously as it would with an AMD loader.
+  ///
+  /// require(['a', 'b'], function (a, b) { ... })
+  ///   => Promise.resolve().then(function () { return (function (a, b) { ... })(require('a'), require('b')); })
+  fn fold_amd_require(&mut self, node: ast::CallExpr) -> ast::CallExpr {
+    let mut args = node.args.into_iter();
+    let deps = match args.next().map(|arg| *arg.expr) {
+      Some(ast::Expr::Array(deps)) => deps,
+      _ => unreachable!(),
+    };
+
+    let stmts = match args.next() {
+      Some(callback) if callback.spread.is_none() => {
+        let params = amd_factory_params(&callback.expr);
+        let args = self.fold_amd_deps(deps, &params);
+        vec![ast::Stmt::Return(ast::ReturnStmt {
+          span: DUMMY_SP,
+          arg: Some(Box::new(ast::Expr::Call(ast::CallExpr {
+            span: DUMMY_SP,
+            callee: ast::Callee::Expr(Box::new(ast::Expr::Paren(ast::ParenExpr {
+              span: DUMMY_SP,
+              expr: callback.expr.fold_with(self),
+            }))),
+            args,
+            type_args: None,
+          }))),
+        })]
+      }
+      _ => self
+        .fold_amd_deps(deps, &[])
+        .into_iter()
+        .map(|arg| {
+          ast::Stmt::Expr(ast::ExprStmt {
+            span: DUMMY_SP,
+            expr: arg.expr,
+          })
+        })
+        .collect(),
+    };
+
+    create_promise_then(stmts, node.span)
+  }
+
+  /// Converts an AMD dependency array into arguments for the factory function.
+  /// `require`, `exports` and `module` refer to the CommonJS bindings, and all
+  /// other dependencies become require calls.
+  fn fold_amd_deps(
+    &mut self,
+    deps: ast::ArrayLit,
+    params: &[Option<Id>],
+  ) -> Vec<ast::ExprOrSpread> {
+    deps
+      .elems
+      .into_iter()
+      .enumerate()
+      .map(|(i, elem)| {
+        let expr = match elem {
+          Some(ast::ExprOrSpread { spread: None, expr }) => match *expr {
+            ast::Expr::Lit(ast::Lit::Str(str_)) => match &*str_.value {
+              "require" | "exports" | "module" => {
+                if &*str_.value == "require" {
+                  if let Some(Some(id)) = params.get(i) {
+                    self.amd_require.insert(id.clone());
+                  }
+                }
+                ast::Expr::Ident(ast::Ident::new(str_.value, DUMMY_SP))
+              }
+              _ => ast::Expr::Call(self.fold_call_expr(ast::CallExpr {
+                span: str_.span,
+                callee: ast::Callee::Expr(Box::new(ast::Expr::Ident(ast::Ident::new(
+                  js_word!("require"),
+                  DUMMY_SP,
+                )))),
+                args: vec![ast::ExprOrSpread {
+                  spread: None,
+                  expr: Box::new(ast::Expr::Lit(ast::Lit::Str(str_))),
+                }],
+                type_args: None,
+              })),
+            },
+            expr => expr.fold_with(self),
+          },
+          _ => ast::Expr::Ident(ast::Ident::new(js_word!("undefined"), DUMMY_SP)),
+        };
+        ast::ExprOrSpread {
+          spread: None,
+          expr: Box::new(expr),
+        }
+      })
+      .collect()
+  }
+}
+
+/// Matches `new URL(specifier, import.meta.url)` without reporting `import.meta` usage.
+/// Classic worker scripts can use this form in importScripts() since it is compiled away.
+fn match_script_url(expr: &ast::Expr, decls: &HashSet<Id>) -> Option<(JsWord, Span)> {
+  let new = match expr {
+    ast::Expr::New(new) => new,
+    _ => return None,
+  };
+
+  match &*new.callee {
+    ast::Expr::Ident(id) if id.sym == js_word!("URL") && !decls.contains(&id.to_id()) => {}
+    _ => return None,
+  }
+
+  match new.args.as_deref() {
+    Some([specifier, base]) => match &*base.expr {
+      ast::Expr::Member(member)
+        if is_import_meta(&member.obj)
+          && matches!(match_property_name(member), Some((name, _)) if name == js_word!("url")) =>
+      {
+        match_str(&specifier.expr)
+      }
+      _ => None,
+    },
+    _ => None,
+  }
+}
+
+impl<'a> DependencyCollector<'a> {
+  fn external(&self, specifier: &str) -> Option<&'a External> {
+    let config: &'a Config = self.config;
+    match_external(&config.externals, specifier)
+  }
+
+  /// Creates a `require` call that is left as is rather than becoming a dependency.
+  fn create_external_require(&self, specifier: ast::Str) -> ast::Expr {
+    ast::Expr::Call(ast::CallExpr {
+      span: DUMMY_SP,
+      callee: ast::Callee::Expr(Box::new(ast::Expr::Ident(ast::Ident::new(
+        js_word!("require"),
+        DUMMY_SP.apply_mark(self.ignore_mark),
+      )))),
+      args: vec![ast::ExprOrSpread {
+        spread: None,
+        expr: Box::new(ast::Expr::Lit(ast::Lit::Str(specifier))),
+      }],
+      type_args: None,
+    })
+  }
+
+  /// Replaces a `require` or `import()` of an external with the external reference.
+  fn fold_external_call(&mut self, call: &ast::CallExpr) -> Option<ast::Expr> {
+    let is_import = match &call.callee {
+      Callee::Import(_) => true,
+      Callee::Expr(expr) => match &**expr {
+        ast::Expr::Ident(ident)
+          if ident.sym == js_word!("require") && !self.decls.contains(&ident.to_id()) =>
+        {
+          false
+        }
+        _ => return None,
+      },
+      _ => return None,
+    };
+
+    let specifier = match call.args.first().map(|arg| &*arg.expr) {
+      Some(ast::Expr::Lit(ast::Lit::Str(str_))) => str_.clone(),
+      _ => return None,
+    };
+    let external = self.external(&specifier.value)?;
+
+    let expr = match external {
+      External::Global(name) => ast::Expr::Ident(ast::Ident::new(name.as_str().into(), DUMMY_SP)),
+      External::Import if is_import => {
+        let mut call = call.clone();
+        call.callee = ast::Callee::Expr(Box::new(ast::Expr::Ident(ast::Ident::new(
+          "import".into(),
+          DUMMY_SP.apply_mark(self.ignore_mark),
+        ))));
+        return Some(ast::Expr::Call(call));
+      }
+      _ => self.create_external_require(specifier),
+    };
+
+    if !is_import {
+      return Some(expr);
+    }
+
+    // import('react') -> Promise.resolve(React)
+    Some(ast::Expr::Call(ast::CallExpr {
+      span: call.span,
+      callee: ast::Callee::Expr(Box::new(ast::Expr::Member(ast::MemberExpr {
+        span: DUMMY_SP,
+        obj: Box::new(ast::Expr::Ident(ast::Ident::new(
+          "Promise".into(),
+          DUMMY_SP,
+        ))),
+        prop: MemberProp::Ident(ast::Ident::new("resolve".into(), DUMMY_SP)),
+      }))),
+      args: vec![ast::ExprOrSpread {
+        spread: None,
+        expr: Box::new(expr),
+      }],
+      type_args: None,
+    }))
+  }
+
+  /// Rewrites static imports and re-exports of externals so they never become dependencies.
+  /// Re-exports are split into an import and a local export first.
+  fn fold_external_module_item(&mut self, item: ast::ModuleItem) -> Vec<ast::ModuleItem> {
+    use ast::{ModuleDecl, ModuleItem};
+
+    let decl = match item {
+      ModuleItem::ModuleDecl(decl) => decl,
+      item => return vec![item],
+    };
+
+    match decl {
+      ModuleDecl::Import(import) if !import.type_only => match self.external(&import.src.value) {
+        Some(external) => self.fold_external_import(import, external),
+        None => vec![ModuleItem::ModuleDecl(ModuleDecl::Import(import))],
+      },
+      ModuleDecl::ExportNamed(ast::NamedExport {
+        span,
+        specifiers,
+        src: Some(src),
+        type_only: false,
+        asserts,
+      }) if self.external(&src.value).is_some() => {
+        let external = self.external(&src.value).unwrap();
+        let local = |name: &JsWord| {
+          ast::Ident::new(
+            format!(
+              "${}$external${:x}${}",
+              self.config.module_id,
+              hash!(src.value),
+              name
+            )
+            .into(),
+            DUMMY_SP,
+          )
+        };
+
+        let mut import_specifiers = vec![];
+        let mut export_specifiers = vec![];
+        for specifier in specifiers {
+          let (imported, exported) = match specifier {
+            ast::ExportSpecifier::Named(named) => {
+              let orig = named.orig;
+              let exported = named.exported.unwrap_or_else(|| orig.clone());
+              (Some(orig), exported)
+            }
+            ast::ExportSpecifier::Namespace(namespace) => (None, namespace.name),
+            ast::ExportSpecifier::Default(default) => (
+              Some(ast::ModuleExportName::Ident(ast::Ident::new(
+                js_word!("default"),
+                DUMMY_SP,
+              ))),
+              ast::ModuleExportName::Ident(default.exported),
+            ),
+          };
+
+          let local = match &imported {
+            Some(imported) => local(&match_export_name(imported).0),
+            None => local(&"*".into()),
+          };
+          import_specifiers.push(match imported {
+            Some(imported) => ast::ImportSpecifier::Named(ast::ImportNamedSpecifier {
+              span: DUMMY_SP,
+              local: local.clone(),
+              imported: Some(imported),
+              is_type_only: false,
+            }),
+            None => ast::ImportSpecifier::Namespace(ast::ImportStarAsSpecifier {
+              span: DUMMY_SP,
+              local: local.clone(),
+            }),
+          });
+          export_specifiers.push(ast::ExportSpecifier::Named(ast::ExportNamedSpecifier {
+            span: DUMMY_SP,
+            orig: ast::ModuleExportName::Ident(local),
+            exported: Some(exported),
+            is_type_only: false,
+          }));
+        }
+
+        let import = ast::ImportDecl {
+          span,
+          specifiers: import_specifiers,
+          src,
+          type_only: false,
+          asserts,
+        };
+        let mut items = self.fold_external_import(import, external);
+        items.push(ModuleItem::ModuleDecl(ModuleDecl::ExportNamed(
+          ast::NamedExport {
+            span,
+            specifiers: export_specifiers,
+            src: None,
+            type_only: false,
+            asserts: None,
+          },
+        )));
+        items
+      }
+      ModuleDecl::ExportAll(export) if self.external(&export.src.value).is_some() => {
+        self.diagnostics.push(Diagnostic {
+          message: "Re-exporting all exports of an external is not supported.".to_string(),
+          code_highlights: Some(vec![CodeHighlight {
+            message: None,
+            loc: SourceLocation::from(self.source_map, export.src.span),
+          }]),
+          hints: Some(vec![String::from(
+            "Re-export the names you need explicitly, e.g. export {a, b} from 'external'",
+          )]),
+          show_environment: false,
+          severity: DiagnosticSeverity::Error,
+          documentation_url: None,
+        });
+        vec![ModuleItem::ModuleDecl(ModuleDecl::ExportAll(export))]
+      }
+      decl => vec![ModuleItem::ModuleDecl(decl)],
+    }
+  }
+
+  /// Compiles an import of an external. `import` externals are kept as native imports
+  /// when scope hoisting or outputting ES modules. Otherwise, the bindings are declared
+  /// as variables pointing to the external.
+  ///
+  /// import React, {useState} from 'react'
+  ///   => var $id$external$hash = require('react'), React = ..., useState = $id$external$hash.useState;
+  fn fold_external_import(
+    &mut self,
+    mut import: ast::ImportDecl,
+    external: &External,
+  ) -> Vec<ast::ModuleItem> {
+    use ast::{ModuleDecl, ModuleItem};
+
+    // Without scope hoisting, every asset is wrapped in a function where imports are not
+    // allowed, so the external is loaded with require instead.
+    if *external == External::Import && self.config.scope_hoist {
+      // Scope hoisted imports end up in the output as is, which must be an ES module.
+      if !self.config.is_esm_output {
+        self.diagnostics.push(Diagnostic {
+          message: format!(
+            "External \"{}\" is loaded with import, which requires an ES module output format.",
+            import.src.value
+          ),
+          code_highlights: Some(vec![CodeHighlight {
+            message: None,
+            loc: SourceLocation::from(self.source_map, import.src.span),
+          }]),
+          hints: Some(vec![String::from(
+            "Use the \"require\" or \"global\" external type for this module instead.",
+          )]),
+          show_environment: true,
+          severity: DiagnosticSeverity::Error,
+          documentation_url: None,
+        });
+      }
+
+      // Mark the import so it is not compiled, and make named imports explicit
+      // in case the local binding is renamed.
+      import.span = import.span.apply_mark(self.ignore_mark);
+      for specifier in &mut import.specifiers {
+        if let ast::ImportSpecifier::Named(named) = specifier {
+          if named.imported.is_none() {
+            named.imported = Some(ast::ModuleExportName::Ident(ast::Ident::new(
+              named.local.sym.clone(),
+              DUMMY_SP,
+            )));
+          }
+        }
+      }
+      return vec![ModuleItem::ModuleDecl(ModuleDecl::Import(import))];
+    }
+
+    let source = match external {
+      External::Global(name) => ast::Expr::Ident(ast::Ident::new(name.as_str().into(), DUMMY_SP)),
+      _ => self.create_external_require(import.src.clone()),
+    };
+
+    // Side effect imports
+    if import.specifiers.is_empty() {
+      return match external {
+        External::Global(_) => vec![],
+        _ => vec![ModuleItem::Stmt(ast::Stmt::Expr(ast::ExprStmt {
+          span: import.span,
+          expr: Box::new(source),
+        }))],
+      };
+    }
+
+    let namespace = ast::Ident::new(
+      format!(
+        "${}$external${:x}",
+        self.config.module_id,
+        hash!(import.src.value)
+      )
+      .into(),
+      DUMMY_SP,
+    );
+    let member = |prop: JsWord| {
+      ast::Expr::Member(ast::MemberExpr {
+        span: DUMMY_SP,
+        obj: Box::new(ast::Expr::Ident(namespace.clone())),
+        prop: MemberProp::Ident(ast::Ident::new(prop, DUMMY_SP)),
+      })
+    };
 
-    // Replace the original require node with a reference to a variable `res`,
-    // which will be added as a parameter to the parent function.
-    if let ast::Expr::Call(call) = &node {
-      if let Some(require_node) = &self.require_node {
-        if require_node == call {
-          return ast::Expr::Ident(ast::Ident::new("res".into(), DUMMY_SP));
+    let mut decls = vec![(namespace.clone(), source)];
+    for specifier in import.specifiers {
+      decls.push(match specifier {
+        ast::ImportSpecifier::Named(named) => {
+          let imported = match &named.imported {
+            Some(imported) => match_export_name(imported).0.clone(),
+            None => named.local.sym.clone(),
+          };
+          (named.local, member(imported))
         }
-      }
+        // ns.__esModule ? ns.default : ns
+        ast::ImportSpecifier::Default(default) => (
+          default.local,
+          ast::Expr::Cond(ast::CondExpr {
+            span: DUMMY_SP,
+            test: Box::new(member("__esModule".into())),
+            cons: Box::new(member(js_word!("default"))),
+            alt: Box::new(ast::Expr::Ident(namespace.clone())),
+          }),
+        ),
+        ast::ImportSpecifier::Namespace(ns) => (ns.local, ast::Expr::Ident(namespace.clone())),
+      });
     }
 
-    node
+    vec![ModuleItem::Stmt(ast::Stmt::Decl(ast::Decl::Var(
+      ast::VarDecl {
+        span: import.span,
+        kind: ast::VarDeclKind::Var,
+        declare: false,
+        decls: decls
+          .into_iter()
+          .map(|(name, init)| ast::VarDeclarator {
+            span: DUMMY_SP,
+            name: ast::Pat::Ident(ast::BindingIdent::from(name)),
+            init: Some(Box::new(init)),
+            definite: false,
+          })
+          .collect(),
+      },
+    )))]
   }
 }
 
+/// Returns the bindings of an AMD factory function's parameters.
+fn amd_factory_params(factory: &ast::Expr) -> Vec<Option<Id>> {
+  let pats: Vec<&ast::Pat> = match factory {
+    ast::Expr::Fn(func) => func
+      .function
+      .params
+      .iter()
+      .map(|param| &param.pat)
+      .collect(),
+    ast::Expr::Arrow(arrow) => arrow.params.iter().collect(),
+    _ => return vec![],
+  };
+
+  pats
+    .into_iter()
+    .map(|pat| match pat {
+      ast::Pat::Ident(binding) => Some(binding.id.to_id()),
+      _ => None,
+    })
+    .collect()
+}
+
 impl<'a> DependencyCollector<'a> {
   fn match_new_url(
     &mut self,
@@ -1142,6 +2926,86 @@ impl<'a> DependencyCollector<'a> {
     None
   }
 
+  /// Matches `require.resolve(specifier)` and `import.meta.resolve(specifier)`.
+  /// Returns the specifier, its span, and whether this is a require.resolve call.
+  fn match_resolve_call(&mut self, call: &ast::CallExpr) -> Option<(JsWord, Span, bool)> {
+    let member = match &call.callee {
+      Callee::Expr(expr) => match &**expr {
+        ast::Expr::Member(member) => member,
+        _ => return None,
+      },
+      _ => return None,
+    };
+
+    let is_require = match_member_expr(member, vec!["require", "resolve"], self.decls);
+    let is_import_meta_resolve = matches!(match_property_name(member), Some((name, _)) if &*name == "resolve")
+      && is_import_meta(&member.obj);
+    if !is_require && !is_import_meta_resolve {
+      return None;
+    }
+
+    match call.args.first() {
+      Some(arg) if arg.spread.is_none() => {
+        match_str(&arg.expr).map(|(specifier, span)| (specifier, span, is_require))
+      }
+      _ => None,
+    }
+  }
+
+  /// Replaces a resolve call with the URL of the resolved file. require.resolve returns a
+  /// file path rather than a URL in Node, so the URL is converted with fileURLToPath.
+  fn fold_resolve_call(
+    &mut self,
+    specifier: JsWord,
+    span: Span,
+    call_span: Span,
+    is_require: bool,
+  ) -> ast::Expr {
+    let url = self.add_url_dependency(
+      specifier,
+      span,
+      DependencyKind::Url,
+      self.config.source_type,
+    );
+
+    // Library builds produce a URL object, otherwise the JSRuntime returns a string.
+    let url = if self.config.is_library {
+      ast::Expr::Member(ast::MemberExpr {
+        span: DUMMY_SP,
+        obj: Box::new(url),
+        prop: MemberProp::Ident(ast::Ident::new("href".into(), DUMMY_SP)),
+      })
+    } else {
+      url
+    };
+
+    if !is_require || !self.config.context.is_node() {
+      return url;
+    }
+
+    self.add_dependency(
+      "url".into(),
+      call_span,
+      DependencyKind::Require,
+      None,
+      false,
+      self.config.source_type,
+    );
+    ast::Expr::Call(ast::CallExpr {
+      span: DUMMY_SP,
+      callee: ast::Callee::Expr(Box::new(ast::Expr::Member(ast::MemberExpr {
+        span: DUMMY_SP,
+        obj: Box::new(ast::Expr::Call(self.create_require("url".into()))),
+        prop: MemberProp::Ident(ast::Ident::new("fileURLToPath".into(), DUMMY_SP)),
+      }))),
+      args: vec![ast::ExprOrSpread {
+        spread: None,
+        expr: Box::new(url),
+      }],
+      type_args: None,
+    })
+  }
+
   #[allow(clippy::wrong_self_convention)]
   fn is_import_meta_url(&mut self, expr: &ast::Expr) -> bool {
     use ast::*;
@@ -1294,6 +3158,242 @@ impl<'a> DependencyCollector<'a> {
   }
 }
 
+/// Local bindings referring to the Node builtins that the collector needs to recognize.
+#[derive(Default)]
+struct NodeBuiltins {
+  /// Maps a local binding to its builtin module (without the `node:` prefix),
+  /// and the imported name, or `*` for the module object itself.
+  imports: HashMap<Id, (JsWord, JsWord)>,
+  /// Bindings holding the function returned by `createRequire(import.meta.url)`.
+  create_require: HashSet<Id>,
+}
+
+impl NodeBuiltins {
+  fn collect(module: &ast::Module, decls: &HashSet<Id>, ignore_mark: Mark) -> Self {
+    let mut collector = NodeBuiltinCollector {
+      decls,
+      ignore_mark,
+      builtins: NodeBuiltins::default(),
+    };
+    module.visit_with(&mut collector);
+    collector.builtins
+  }
+
+  fn insert(&mut self, local: &ast::Ident, source: &JsWord, imported: JsWord) {
+    let source = source.strip_prefix("node:").unwrap_or(source);
+    self
+      .imports
+      .insert(local.to_id(), (source.into(), imported));
+  }
+
+  /// Matches `name` or `module.name`, where either binding comes from the builtin `source`.
+  fn matches(&self, expr: &ast::Expr, source: &str, name: &str) -> bool {
+    match expr {
+      ast::Expr::Ident(ident) => matches!(
+        self.imports.get(&ident.to_id()),
+        Some((s, imported)) if s == source && imported == name
+      ),
+      ast::Expr::Member(member) => match &*member.obj {
+        ast::Expr::Ident(ident) => {
+          matches!(
+            self.imports.get(&ident.to_id()),
+            Some((s, imported)) if s == source && imported == "*"
+          ) && matches!(match_property_name(member), Some((prop, _)) if &*prop == name)
+        }
+        _ => false,
+      },
+      _ => false,
+    }
+  }
+}
+
+struct NodeBuiltinCollector<'a> {
+  decls: &'a HashSet<Id>,
+  ignore_mark: Mark,
+  builtins: NodeBuiltins,
+}
+
+impl<'a> Visit for NodeBuiltinCollector<'a> {
+  fn visit_module(&mut self, node: &ast::Module) {
+    // Imports are hoisted, so collect them before visiting the rest of the module.
+    for item in &node.body {
+      if let ast::ModuleItem::ModuleDecl(ast::ModuleDecl::Import(import)) = item {
+        if import.type_only {
+          continue;
+        }
+
+        for specifier in &import.specifiers {
+          match specifier {
+            ast::ImportSpecifier::Named(named) => {
+              let imported = match &named.imported {
+                Some(imported) => match_export_name(imported).0,
+                None => named.local.sym.clone(),
+              };
+              self
+                .builtins
+                .insert(&named.local, &import.src.value, imported);
+            }
+            ast::ImportSpecifier::Default(default) => {
+              self
+                .builtins
+                .insert(&default.local, &import.src.value, "*".into());
+            }
+            ast::ImportSpecifier::Namespace(namespace) => {
+              self
+                .builtins
+                .insert(&namespace.local, &import.src.value, "*".into());
+            }
+          }
+        }
+      }
+    }
+
+    node.visit_children_with(self);
+  }
+
+  fn visit_var_declarator(&mut self, node: &ast::VarDeclarator) {
+    if let Some(init) = &node.init {
+      // const x = require('x'), const {a, b: c} = require('x')
+      if let Some(source) = match_require(init, self.decls, self.ignore_mark) {
+        match &node.name {
+          ast::Pat::Ident(binding) => {
+            self.builtins.insert(&binding.id, &source, "*".into());
+          }
+          ast::Pat::Object(object) => {
+            for prop in &object.props {
+              match prop {
+                ast::ObjectPatProp::Assign(assign) => {
+                  self
+                    .builtins
+                    .insert(&assign.key, &source, assign.key.sym.clone());
+                }
+                ast::ObjectPatProp::KeyValue(kv) => {
+                  if let (ast::PropName::Ident(key), ast::Pat::Ident(binding)) =
+                    (&kv.key, &*kv.value)
+                  {
+                    self.builtins.insert(&binding.id, &source, key.sym.clone());
+                  }
+                }
+                ast::ObjectPatProp::Rest(_) => {}
+              }
+            }
+          }
+          _ => {}
+        }
+      }
+
+      // const require = createRequire(import.meta.url)
+      if let (ast::Pat::Ident(binding), ast::Expr::Call(call)) = (&node.name, &**init) {
+        let is_import_meta_url = match call.args.first().map(|arg| &*arg.expr) {
+          Some(ast::Expr::Member(member)) => {
+            is_import_meta(&member.obj)
+              && matches!(match_property_name(member), Some((name, _)) if name == js_word!("url"))
+          }
+          _ => false,
+        };
+
+        let is_create_require = match &call.callee {
+          Callee::Expr(callee) => self.builtins.matches(callee, "module", "createRequire"),
+          _ => false,
+        };
+
+        if is_import_meta_url && is_create_require {
+          self.builtins.create_require.insert(binding.id.to_id());
+        }
+      }
+    }
+
+    node.visit_children_with(self);
+  }
+}
+
+/// Returns the key/value properties of an object literal with a string key.
+fn match_key_values(obj: &ast::ObjectLit) -> impl Iterator<Item = (JsWord, &ast::Expr)> {
+  obj.props.iter().filter_map(|prop| {
+    let kv = match prop {
+      ast::PropOrSpread::Prop(prop) => match &**prop {
+        ast::Prop::KeyValue(kv) => kv,
+        _ => return None,
+      },
+      _ => return None,
+    };
+
+    match &kv.key {
+      ast::PropName::Ident(ast::Ident { sym, .. })
+      | ast::PropName::Str(ast::Str { value: sym, .. }) => Some((sym.clone(), &*kv.value)),
+      _ => None,
+    }
+  })
+}
+
+/// Converts import attributes to a map of string values, e.g. `{type: 'json'}`.
+fn match_import_attributes(obj: &ast::ObjectLit) -> HashMap<JsWord, JsWord> {
+  match_key_values(obj)
+    .filter_map(|(k, value)| match_str(value).map(|(v, _)| (k, v)))
+    .collect()
+}
+
+/// Reads the options object of a dynamic import, e.g. `{with: {type: 'json'}, preload: true}`.
+/// Attributes come from the `with` (or legacy `assert`) key, and `preload` or `prefetch`
+/// are equivalent to the `webpackPreload` and `webpackPrefetch` magic comments.
+fn match_import_options(
+  obj: &ast::ObjectLit,
+) -> (Option<HashMap<JsWord, JsWord>>, Option<ResourceHint>) {
+  let mut attributes = None;
+  let mut resource_hint = None;
+  for (k, value) in match_key_values(obj) {
+    match (&*k, value) {
+      ("with" | "assert", ast::Expr::Object(obj)) => {
+        attributes = Some(match_import_attributes(obj));
+      }
+      ("preload", ast::Expr::Lit(ast::Lit::Bool(ast::Bool { value: true, .. }))) => {
+        resource_hint = Some(ResourceHint::Preload);
+      }
+      ("prefetch", ast::Expr::Lit(ast::Lit::Bool(ast::Bool { value: true, .. })))
+        if resource_hint.is_none() =>
+      {
+        resource_hint = Some(ResourceHint::Prefetch);
+      }
+      _ => {}
+    }
+  }
+
+  (attributes, resource_hint)
+}
+
+/// Escapes characters with a special meaning in globs, so a path is matched literally.
+fn escape_glob(path: &str) -> String {
+  let mut escaped = String::with_capacity(path.len());
+  for c in path.chars() {
+    if matches!(c, '*' | '?' | '[' | ']' | '{' | '}' | '(' | ')' | '\\') {
+      escaped.push('\\');
+    }
+    escaped.push(c);
+  }
+  escaped
+}
+
+/// Whether the expression is an `import()` call, optionally followed by `.then()`, `.catch()` or `.finally()` calls.
+fn is_import_chain(expr: &ast::Expr) -> bool {
+  match expr {
+    ast::Expr::Call(call) => match &call.callee {
+      Callee::Import(_) => true,
+      Callee::Expr(callee) => match &**callee {
+        ast::Expr::Member(member) => {
+          matches!(
+            match_property_name(member),
+            Some((name, _)) if &*name == "then" || &*name == "catch" || &*name == "finally"
+          ) && is_import_chain(&member.obj)
+        }
+        _ => false,
+      },
+      _ => false,
+    },
+    ast::Expr::Paren(paren) => is_import_chain(&paren.expr),
+    _ => false,
+  }
+}
+
 // matches the `type: 'module'` option of workers
 fn match_worker_type(expr: Option<&ast::ExprOrSpread>) -> (SourceType, Option<ast::ExprOrSpread>) {
   use ast::*;
@@ -1363,3 +3463,909 @@ fn match_worker_type(expr: Option<&ast::ExprOrSpread>) -> (SourceType, Option<as
 
   (SourceType::Script, expr.cloned())
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_utils::{config, dependencies, specifiers};
+  use crate::{transform, TransformResult};
+
+  #[test]
+  fn create_require() {
+    let mut node = config(
+      r#"
+    import {createRequire} from 'node:module';
+    import * as module from 'module';
+    const require = createRequire(import.meta.url);
+    const req = module.createRequire(import.meta.url);
+    const other = createRequire('/foo');
+    require('./native.node');
+    req('./b');
+    other('./c');
+    "#,
+    );
+    node.context = EnvironmentContext::Node;
+    let result = transform(node).unwrap();
+    assert_eq!(
+      dependencies(&result),
+      vec![
+        "Import node:module",
+        "Import module",
+        "Require ./native.node",
+        "Require ./b"
+      ]
+    );
+    let code = String::from_utf8(result.code).unwrap();
+    assert!(code.contains("require(\"./native.node\");"));
+    assert!(code.contains("require(\"./b\");"));
+    assert!(code.contains("other(\"./c\");"));
+  }
+
+  #[test]
+  fn node_entries() {
+    let mut node = config(
+      r#"
+    import {Worker} from 'node:worker_threads';
+    const child_process = require('child_process');
+    new Worker(new URL('./worker.js', import.meta.url), {workerData: 1});
+    child_process.fork(new URL('./child.js', import.meta.url), ['--foo']);
+    new Worker('./path.js');
+    "#,
+    );
+    node.context = EnvironmentContext::Node;
+    let result = transform(node).unwrap();
+    assert_eq!(
+      dependencies(&result),
+      vec![
+        "Import node:worker_threads",
+        "Require child_process",
+        "NodeWorker ./worker.js",
+        "ChildProcess ./child.js"
+      ]
+    );
+    let code = String::from_utf8(result.code).unwrap();
+    assert_eq!(code.matches("new URL(require(").count(), 2);
+    assert!(result.diagnostics.is_none());
+  }
+
+  #[test]
+  fn import_attributes() {
+    let code = r#"
+    import data from './data.json' with {type: 'json'};
+    export {default as styles} from './styles.css' assert {type: 'css'};
+    export * from './all.json' with {type: 'json'};
+    import './plain';
+    import('./lazy.json', {with: {type: 'json'}, preload: true});
+    console.log(data);
+    "#;
+
+    let attributes = |result: &TransformResult, specifier: &str| {
+      let dep = result
+        .dependencies
+        .iter()
+        .find(|dep| dep.specifier == *specifier)
+        .unwrap();
+      dep.attributes.as_ref().map(|attrs| {
+        let mut attrs: Vec<_> = attrs.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+        attrs.sort();
+        attrs
+      })
+    };
+
+    let mut typescript = config(code);
+    typescript.is_type_script = true;
+    for result in [transform(config(code)), transform(typescript)] {
+      let result = result.unwrap();
+      assert!(result.diagnostics.is_none());
+      assert_eq!(
+        attributes(&result, "./data.json"),
+        Some(vec!["type=json".into()])
+      );
+      assert_eq!(
+        attributes(&result, "./styles.css"),
+        Some(vec!["type=css".into()])
+      );
+      assert_eq!(
+        attributes(&result, "./all.json"),
+        Some(vec!["type=json".into()])
+      );
+      assert_eq!(attributes(&result, "./plain"), None);
+      assert_eq!(
+        attributes(&result, "./lazy.json"),
+        Some(vec!["type=json".into()])
+      );
+    }
+  }
+
+  #[test]
+  fn glob_dependencies() {
+    let result = transform(config(
+      r#"
+    const pages = import.meta.glob('./pages/*.js');
+    const raw = import.meta.glob(['./a/*.md', './b/*.md', '!./a/skip.md'], {eager: true, query: '?raw', import: 'default'});
+    const icons = require.context('./icons/', false, /\.svg$/i);
+    const lazy = require.context('./lazy', true, undefined, 'lazy');
+    "#,
+    ))
+    .unwrap();
+
+    let globs: Vec<_> = result
+      .dependencies
+      .iter()
+      .filter(|dep| dep.kind == DependencyKind::Glob)
+      .map(|dep| (&*dep.specifier, dep.glob.clone().unwrap()))
+      .collect();
+
+    assert_eq!(
+      globs.iter().map(|(s, _)| *s).collect::<Vec<_>>(),
+      vec![
+        "./pages/*.js",
+        "./a/*.md",
+        "./b/*.md",
+        "./icons/*",
+        "./lazy/**/*"
+      ]
+    );
+    assert_eq!(globs[0].1, GlobOptions::default());
+    assert_eq!(
+      globs[1].1,
+      GlobOptions {
+        eager: true,
+        import: Some("default".into()),
+        query: Some("?raw".into()),
+        ignore: vec!["./a/skip.md".into()],
+        ..Default::default()
+      }
+    );
+    assert_eq!(
+      globs[3].1,
+      GlobOptions {
+        eager: true,
+        filter: Some(("\\.svg$".into(), "i".into())),
+        context: true,
+        ..Default::default()
+      }
+    );
+    assert!(!globs[4].1.eager);
+    assert!(result.diagnostics.is_none());
+
+    let code = String::from_utf8(result.code).unwrap();
+    assert!(code.contains("const raw = Object.assign({}"));
+    assert!(!code.contains("import.meta.glob('./"));
+    assert!(!code.contains("require.context"));
+
+    let result = transform(config("import.meta.glob(name);")).unwrap();
+    assert_eq!(result.diagnostics.map(|d| d.len()), Some(1));
+  }
+
+  #[test]
+  fn partial_glob_imports() {
+    let result = transform(config(
+      r#"
+    import(`./locales/${lang}.json`);
+    import('../pages/' + section + '/' + page + '.js');
+    import(`./${name}`);
+    import(url);
+    import(`./[id]/${page}.js`);
+    import(`lodash/${name}`);
+    "#,
+    ))
+    .unwrap();
+
+    assert_eq!(
+      dependencies(&result),
+      vec![
+        "Glob ./locales/*.json",
+        "Glob ../pages/*/*.js",
+        "Glob ./\\[id\\]/*.js"
+      ]
+    );
+
+    let diagnostics = result.diagnostics.unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].severity, DiagnosticSeverity::Warning);
+    assert!(diagnostics[0].message.contains("\"./*\""));
+
+    let code = String::from_utf8(result.code).unwrap();
+    assert!(code.contains("Promise.resolve().then(function() {"));
+    assert!(code.contains("[`./locales/${lang}.json`]()"));
+    assert!(code.contains("require(`./${name}`)"));
+    assert!(code.contains("require(`lodash/${name}`)"));
+  }
+
+  #[test]
+  fn magic_comments() {
+    let result = transform(config(
+      r#"
+    import(/* @parcel-ignore */ './ignored');
+    import(/* webpackIgnore: true */ `./${name}`);
+    require(/* webpackIgnore: true */ './ignored-require');
+    import(/* webpackIgnore: false */ './not-ignored');
+    import(/* parcelChunkName: "admin" */ './admin');
+    import(/* webpackChunkName: 'settings', webpackIgnore: false */ './settings');
+    "#,
+    ))
+    .unwrap();
+
+    assert_eq!(
+      result
+        .dependencies
+        .iter()
+        .map(|dep| (&*dep.specifier, dep.bundle_name.as_deref()))
+        .collect::<Vec<_>>(),
+      vec![
+        ("./not-ignored", None),
+        ("./admin", Some("admin")),
+        ("./settings", Some("settings"))
+      ]
+    );
+
+    let code = String::from_utf8(result.code).unwrap();
+    assert!(code.contains("import(/* @parcel-ignore */ \"./ignored\")"));
+    assert!(code.contains("import(/* webpackIgnore: true */ `./${name}`)"));
+    assert!(code.contains("require(/* webpackIgnore: true */ \"./ignored-require\")"));
+  }
+
+  #[test]
+  fn dependency_priority() {
+    let result = transform(config(
+      r#"
+    import './static';
+    import(/* webpackPrefetch: true */ './prefetch');
+    import(/* webpackPreload */ './preload');
+    import(/* webpackPrefetch: false */ './lazy');
+    import('./attribute', {preload: true});
+    import(/* parcelPriority: "parallel" */ './parallel');
+    "#,
+    ))
+    .unwrap();
+
+    assert_eq!(
+      result
+        .dependencies
+        .iter()
+        .map(|dep| (&*dep.specifier, dep.priority, dep.resource_hint))
+        .collect::<Vec<_>>(),
+      vec![
+        ("./static", Priority::Sync, None),
+        ("./prefetch", Priority::Lazy, Some(ResourceHint::Prefetch)),
+        ("./preload", Priority::Lazy, Some(ResourceHint::Preload)),
+        ("./lazy", Priority::Lazy, None),
+        ("./attribute", Priority::Lazy, Some(ResourceHint::Preload)),
+        ("./parallel", Priority::Parallel, None),
+      ]
+    );
+    // Options other than `with` are not import attributes.
+    assert!(result
+      .dependencies
+      .iter()
+      .all(|dep| dep.attributes.is_none()));
+  }
+
+  #[test]
+  fn optional_dynamic_imports() {
+    let result = transform(config(
+      r#"
+    async function test() {
+      try {
+        await import('try');
+        return import('return').then(m => m.default);
+      } catch {}
+    }
+    try {
+      setTimeout(() => import('deferred'));
+      setTimeout(async function () {
+        await import('nested-function');
+      });
+      import('not-awaited');
+    } catch {}
+    import('catch').catch(() => null);
+    import('then-catch').then(m => import('nested')).catch(() => null);
+    foo(import('argument')).catch(() => null);
+    import(name).catch(() => null);
+    import('required');
+    "#,
+    ))
+    .unwrap();
+
+    assert_eq!(
+      result
+        .dependencies
+        .iter()
+        .map(|dep| (&*dep.specifier, dep.is_optional))
+        .collect::<Vec<_>>(),
+      vec![
+        ("try", true),
+        ("return", true),
+        ("deferred", false),
+        ("nested-function", false),
+        ("not-awaited", false),
+        ("catch", true),
+        ("then-catch", true),
+        ("nested", false),
+        ("argument", false),
+        ("required", false),
+      ]
+    );
+  }
+
+  #[test]
+  fn amd_dependencies() {
+    let result = transform(config(
+      r#"
+    define('name', ['require', 'exports', 'jquery', './util'], function (req, exports, $, util) {
+      exports.foo = req('./lazy');
+    });
+    require(['./a', './b'], function (a, b) {});
+    "#,
+    ))
+    .unwrap();
+    assert_eq!(
+      specifiers(&result),
+      vec!["jquery", "./util", "./lazy", "./a", "./b"]
+    );
+    assert!(result
+      .dependencies
+      .iter()
+      .all(|dep| dep.kind == DependencyKind::Require && !dep.is_helper));
+    let code = std::str::from_utf8(&result.code).unwrap();
+    assert!(!code.contains("define("));
+    assert!(code.contains("module.exports = result"));
+
+    // Simplified CommonJS wrapper
+    let result = transform(config(
+      r#"
+    define(function (require, exports, module) {
+      module.exports = require('./dep');
+    });
+    "#,
+    ))
+    .unwrap();
+    assert_eq!(specifiers(&result), vec!["./dep"]);
+    let code = std::str::from_utf8(&result.code).unwrap();
+    assert!(code.contains(r#"module.exports = require("./dep");"#));
+    assert!(code.contains("}(require, exports, module));"));
+
+    // Only function literals are called directly; identifiers may refer to a value.
+    let result = transform(config(
+      r#"
+    var factory = {foo: 2};
+    define(['./a'], factory);
+    "#,
+    ))
+    .unwrap();
+    assert_eq!(specifiers(&result), vec!["./a"]);
+    let code = std::str::from_utf8(&result.code).unwrap();
+    assert!(code.contains(r#"typeof factory === "function" ? factory(require("./a")) : factory"#));
+
+    // A bound define is left alone.
+    let result = transform(config(
+      r#"
+    const define = () => {};
+    define(['./a'], function (a) {});
+    "#,
+    ))
+    .unwrap();
+    assert!(result.dependencies.is_empty());
+  }
+
+  #[test]
+  fn resolve_dependencies() {
+    let code = r#"
+    const worker = require.resolve('./worker.js');
+    const wasm = import.meta.resolve('./asset.wasm');
+    "#;
+
+    let result = transform(config(code)).unwrap();
+    assert_eq!(
+      dependencies(&result),
+      vec!["Url ./worker.js", "Url ./asset.wasm"]
+    );
+    assert!(result
+      .dependencies
+      .iter()
+      .all(|dep| dep.placeholder.is_some()));
+    let output = String::from_utf8(result.code).unwrap();
+    assert!(!output.contains("resolve("));
+    for dep in &result.dependencies {
+      assert!(output.contains(&format!(
+        "require(\"{}\")",
+        dep.placeholder.as_ref().unwrap()
+      )));
+    }
+
+    // require.resolve returns a file path in Node.
+    let mut node = config(code);
+    node.context = EnvironmentContext::Node;
+    let result = transform(node).unwrap();
+    assert_eq!(
+      specifiers(&result),
+      vec!["./worker.js", "url", "./asset.wasm"]
+    );
+    // The url dependency is resolved from this module rather than as a helper.
+    assert!(result.dependencies.iter().all(|dep| !dep.is_helper));
+    let output = String::from_utf8(result.code).unwrap();
+    assert_eq!(output.matches("fileURLToPath(").count(), 1);
+    assert!(output.contains(r#"const worker = require("url").fileURLToPath(require(""#));
+
+    // Bare specifiers are resolved from node_modules at runtime in Node.
+    let mut node = config("const pkg = require.resolve('lodash/package.json');");
+    node.context = EnvironmentContext::Node;
+    let result = transform(node).unwrap();
+    assert!(result.dependencies.is_empty());
+    let output = String::from_utf8(result.code).unwrap();
+    assert!(output.contains(r#"require.resolve("lodash/package.json")"#));
+  }
+
+  #[test]
+  fn wasm_dependencies() {
+    let result = transform(config(
+      r#"
+    WebAssembly.instantiateStreaming(fetch('./a.wasm'), imports);
+    WebAssembly.compileStreaming(fetch(new URL('./b.wasm', import.meta.url)));
+    WebAssembly.compileStreaming(fetch('https://example.com/c.wasm'));
+    "#,
+    ))
+    .unwrap();
+    assert_eq!(dependencies(&result), vec!["Url ./a.wasm", "Url ./b.wasm"]);
+
+    // A bare string gets a warning suggesting new URL().
+    let diagnostics = result.diagnostics.unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].severity, DiagnosticSeverity::Warning);
+    assert_eq!(
+      diagnostics[0].hints,
+      Some(vec![
+        "Replace with: new URL('./a.wasm', import.meta.url)".to_string()
+      ])
+    );
+  }
+
+  #[test]
+  fn import_scripts() {
+    let mut worker = config(
+      r#"
+    importScripts(
+      new URL('./a.js', import.meta.url),
+      new URL('./b.js', import.meta.url),
+      'https://example.com/c.js'
+    );
+    "#,
+    );
+    worker.context = EnvironmentContext::WebWorker;
+    worker.source_type = SourceType::Script;
+    let result = transform(worker).unwrap();
+    assert_eq!(
+      dependencies(&result),
+      vec!["ImportScripts ./a.js", "ImportScripts ./b.js"]
+    );
+    assert!(result.diagnostics.is_none());
+    let code = String::from_utf8(result.code).unwrap();
+    assert!(!code.contains("import.meta"));
+
+    // Relative strings are still an error.
+    let mut worker = config("importScripts('https://example.com/a.js', './b.js');");
+    worker.context = EnvironmentContext::WebWorker;
+    worker.source_type = SourceType::Script;
+    let result = transform(worker).unwrap();
+    let diagnostics = result.diagnostics.unwrap();
+    assert_eq!(
+      diagnostics[0].message,
+      "Argument to importScripts() must be a fully qualified URL."
+    );
+  }
+
+  #[test]
+  fn worklets() {
+    let result = transform(config(
+      r#"
+    CSS.paintWorklet.addModule(new URL('./paint.js', import.meta.url));
+    CSS.animationWorklet.addModule(new URL('./animation.js', import.meta.url));
+    CSS.layoutWorklet.addModule(new URL('./layout.js', import.meta.url));
+    audioContext.audioWorklet.addModule(new URL('./processor.js', import.meta.url));
+    "#,
+    ))
+    .unwrap();
+    assert_eq!(
+      result
+        .dependencies
+        .iter()
+        .map(|dep| (dep.kind.clone(), &*dep.specifier, dep.worklet_type))
+        .collect::<Vec<_>>(),
+      vec![
+        (
+          DependencyKind::Worklet,
+          "./paint.js",
+          Some(WorkletType::Paint)
+        ),
+        (
+          DependencyKind::Worklet,
+          "./animation.js",
+          Some(WorkletType::Animation)
+        ),
+        (
+          DependencyKind::Worklet,
+          "./layout.js",
+          Some(WorkletType::Layout)
+        ),
+        (
+          DependencyKind::Worklet,
+          "./processor.js",
+          Some(WorkletType::Audio)
+        ),
+      ]
+    );
+  }
+
+  #[test]
+  fn specifier_types() {
+    let result = transform(config(
+      r#"
+    import React from 'https://esm.sh/react';
+    import data from 'data:text/javascript,export default 1';
+    import lodash from 'npm:lodash';
+    import path from 'jsr:@std/path';
+    import './relative';
+    import 'bare';
+    import '/absolute';
+    "#,
+    ))
+    .unwrap();
+    assert_eq!(
+      result
+        .dependencies
+        .iter()
+        .map(|dep| dep.specifier_type)
+        .collect::<Vec<_>>(),
+      vec![
+        SpecifierType::Url,
+        SpecifierType::Data,
+        SpecifierType::PackageProtocol,
+        SpecifierType::PackageProtocol,
+        SpecifierType::Relative,
+        SpecifierType::Bare,
+        SpecifierType::Absolute,
+      ]
+    );
+    assert!(result.dependencies.iter().all(|dep| !dep.is_external));
+
+    let mut cfg = config(
+      r#"
+    import React from 'https://esm.sh/react';
+    import path from 'jsr:@std/path';
+    import flag from 'jsr:@luca/flag';
+    import('https://esm.sh/react-dom');
+    "#,
+    );
+    cfg.externalize_remote_urls = true;
+    cfg.is_esm_output = true;
+    cfg.protocols.insert("jsr:".into(), "@jsr/".into());
+    // The longest matching protocol wins.
+    cfg.protocols.insert("jsr:@std/".into(), "@std/".into());
+    let result = transform(cfg).unwrap();
+    assert!(result.diagnostics.is_none());
+    assert_eq!(
+      result
+        .dependencies
+        .iter()
+        .map(|dep| (
+          &*dep.specifier,
+          dep.specifier_type,
+          dep.is_external,
+          dep.placeholder.as_deref()
+        ))
+        .collect::<Vec<_>>(),
+      vec![
+        ("https://esm.sh/react", SpecifierType::Url, true, None),
+        (
+          "@std/path",
+          SpecifierType::Bare,
+          false,
+          Some("jsr:@std/path")
+        ),
+        (
+          "@jsr/@luca/flag",
+          SpecifierType::Bare,
+          false,
+          Some("jsr:@luca/flag")
+        ),
+        ("https://esm.sh/react-dom", SpecifierType::Url, true, None),
+      ]
+    );
+    // Externalized dynamic imports are loaded natively.
+    let code = String::from_utf8(result.code).unwrap();
+    assert!(code.contains("import(\"https://esm.sh/react-dom\")"));
+
+    // Static imports of remote URLs can't be loaded in other output formats.
+    let mut cfg = config(
+      r#"
+    import React from 'https://esm.sh/react';
+    import('https://esm.sh/react-dom');
+    "#,
+    );
+    cfg.externalize_remote_urls = true;
+    let result = transform(cfg).unwrap();
+    assert_eq!(result.diagnostics.unwrap().len(), 1);
+  }
+
+  #[test]
+  fn node_builtin_prefix() {
+    let code = r#"
+    import {readFile} from 'node:fs';
+    const path = require('node:path');
+    const lodash = require('lodash');
+    "#;
+
+    let mut modern = config(code);
+    modern.context = EnvironmentContext::Node;
+    modern.targets = Some(HashMap::from([("node".into(), "16.0.0".into())]));
+    let result = transform(modern).unwrap();
+    assert_eq!(
+      result
+        .dependencies
+        .iter()
+        .map(|dep| (&*dep.specifier, dep.is_builtin))
+        .collect::<Vec<_>>(),
+      vec![("node:fs", true), ("node:path", true), ("lodash", false)]
+    );
+    let output = String::from_utf8(result.code).unwrap();
+    assert!(output.contains("require(\"node:fs\")"));
+    assert!(output.contains("require(\"node:path\")"));
+
+    // Older versions of Node don't support the prefix in require calls.
+    let mut legacy = config(code);
+    legacy.context = EnvironmentContext::Node;
+    legacy.targets = Some(HashMap::from([("node".into(), "12.0.0".into())]));
+    let result = transform(legacy).unwrap();
+    assert_eq!(specifiers(&result), vec!["node:fs", "node:path", "lodash"]);
+    let output = String::from_utf8(result.code).unwrap();
+    assert!(output.contains("require(\"fs\")"));
+    assert!(output.contains("require(\"path\")"));
+    assert!(!output.contains("node:"));
+
+    // The prefix was backported to 14.18, but 15.x doesn't support it.
+    let mut node15 = config(code);
+    node15.context = EnvironmentContext::Node;
+    node15.targets = Some(HashMap::from([("node".into(), "15.14.0".into())]));
+    let output = String::from_utf8(transform(node15).unwrap().code).unwrap();
+    assert!(output.contains("require(\"fs\")"));
+    assert!(!output.contains("node:"));
+
+    let mut node14 = config(code);
+    node14.context = EnvironmentContext::Node;
+    node14.targets = Some(HashMap::from([("node".into(), "14.18.0".into())]));
+    let output = String::from_utf8(transform(node14).unwrap().code).unwrap();
+    assert!(output.contains("require(\"node:fs\")"));
+  }
+
+  #[test]
+  fn externals() {
+    let code = r#"
+    import React, {useState} from 'react';
+    import {merge} from 'lodash/fp';
+    import $ from 'jquery';
+    import './styles.css';
+    export {render} from 'react-dom';
+    const moment = require('moment');
+    const chart = import('chart.js');
+    const local = require('./local');
+    console.log(React, useState, merge, $, moment, chart, local);
+    "#;
+
+    let with_externals = |code: &str| {
+      let mut config = config(code);
+      config.externals = HashMap::from([
+        ("react".into(), External::Global("React".into())),
+        ("react-dom".into(), External::Global("ReactDOM".into())),
+        ("lodash/".into(), External::Require),
+        ("jquery".into(), External::Import),
+        ("mom*".into(), External::Require),
+        ("chart.js".into(), External::Import),
+      ]);
+      config
+    };
+
+    let result = transform(with_externals(code)).unwrap();
+    assert!(result.diagnostics.is_none());
+    assert_eq!(specifiers(&result), vec!["./styles.css", "./local"]);
+    let output = String::from_utf8(result.code).unwrap();
+    assert!(output.contains("$abc$external$"));
+    assert!(output.contains("= React"));
+    assert!(output.contains("= ReactDOM"));
+    assert!(output.contains("require(\"lodash/fp\")"));
+    assert!(output.contains("require(\"jquery\")"));
+    assert!(output.contains("require(\"moment\")"));
+    assert!(output.contains("import(\"chart.js\")"));
+
+    // Assets are wrapped in functions without scope hoisting, so imports become requires
+    // even when outputting ES modules.
+    let mut esm = with_externals(code);
+    esm.is_esm_output = true;
+    let result = transform(esm).unwrap();
+    assert!(result.diagnostics.is_none());
+    assert_eq!(specifiers(&result), vec!["./styles.css", "./local"]);
+    let output = String::from_utf8(result.code).unwrap();
+    assert!(output.contains("require(\"jquery\")"));
+    assert!(!output.contains("from \"jquery\""));
+
+    // Imports of externals are kept as native imports when scope hoisting to ES modules.
+    let mut hoisted = with_externals(code);
+    hoisted.scope_hoist = true;
+    hoisted.is_esm_output = true;
+    let result = transform(hoisted).unwrap();
+    assert!(result.diagnostics.is_none());
+    assert_eq!(specifiers(&result), vec!["./styles.css", "./local"]);
+    let output = String::from_utf8(result.code).unwrap();
+    assert!(output.contains("import $abc$var$$ from \"jquery\";"));
+    assert!(output.contains("require(\"lodash/fp\")"));
+
+    // Native imports can't be emitted in other output formats or in wrapped assets.
+    let mut hoisted = with_externals(code);
+    hoisted.scope_hoist = true;
+    let result = transform(hoisted).unwrap();
+    assert_eq!(result.diagnostics.unwrap().len(), 1);
+
+    let mut wrapped = with_externals("import $ from 'jquery'; console.log($, module);");
+    wrapped.scope_hoist = true;
+    wrapped.is_esm_output = true;
+    let result = transform(wrapped).unwrap();
+    assert_eq!(result.diagnostics.unwrap().len(), 1);
+
+    let result = transform(with_externals("export * from 'react';")).unwrap();
+    assert_eq!(result.diagnostics.unwrap().len(), 1);
+  }
+
+  #[test]
+  fn aliases() {
+    let code = r#"
+    import {h} from 'react';
+    import {render} from 'react/jsx-runtime';
+    import Button from '@/components/Button';
+    import lodash from 'lodash';
+    import './local';
+    "#;
+
+    let mut aliased = config(code);
+    aliased.aliases = HashMap::from([
+      ("react".into(), "preact/compat".into()),
+      ("@/".into(), "./src/".into()),
+    ]);
+    aliased.import_map = Some(ImportMap {
+      imports: HashMap::from([("lodash".into(), "lodash-es".into())]),
+      scopes: HashMap::from([(
+        "/src/".into(),
+        HashMap::from([("lodash".into(), "/vendor/lodash.js".into())]),
+      )]),
+    });
+    aliased.scope_hoist = true;
+    let result = transform(aliased).unwrap();
+    assert_eq!(
+      result
+        .dependencies
+        .iter()
+        .map(|dep| (
+          &*dep.specifier,
+          dep.original_specifier.as_deref(),
+          dep.placeholder.as_deref()
+        ))
+        .collect::<Vec<_>>(),
+      vec![
+        ("preact/compat", Some("react"), Some("react")),
+        (
+          "preact/compat/jsx-runtime",
+          Some("react/jsx-runtime"),
+          Some("react/jsx-runtime")
+        ),
+        (
+          "/src/components/Button",
+          Some("@/components/Button"),
+          Some("@/components/Button")
+        ),
+        ("/vendor/lodash.js", Some("lodash"), Some("lodash")),
+        ("./local", None, None),
+      ]
+    );
+
+    // The code refers to the original specifier, which is the dependency's placeholder.
+    let output = String::from_utf8(result.code).unwrap();
+    assert!(output.contains("import \"abc:react\""));
+    assert!(output.contains("import \"abc:@/components/Button\""));
+
+    // Import map keys without a trailing slash only match exactly.
+    let mut mapped = config(code);
+    mapped.import_map = Some(ImportMap {
+      imports: HashMap::from([
+        ("react".into(), "https://esm.sh/react".into()),
+        ("lodash/".into(), "/vendor/lodash/".into()),
+      ]),
+      scopes: HashMap::new(),
+    });
+    let result = transform(mapped).unwrap();
+    assert_eq!(
+      specifiers(&result),
+      vec![
+        "https://esm.sh/react",
+        "react/jsx-runtime",
+        "@/components/Button",
+        "lodash",
+        "./local"
+      ]
+    );
+  }
+
+  #[test]
+  fn side_effect_only_imports() {
+    let code = r#"
+    import './polyfill';
+    import {a} from './a';
+    require('./register');
+    const b = require('./b');
+    console.log(a, b);
+    "#;
+
+    let result = transform(config(code)).unwrap();
+    assert_eq!(
+      result
+        .dependencies
+        .iter()
+        .map(|dep| (&*dep.specifier, dep.is_side_effect_only))
+        .collect::<Vec<_>>(),
+      vec![
+        ("./polyfill", true),
+        ("./a", false),
+        ("./register", true),
+        ("./b", false)
+      ]
+    );
+  }
+
+  #[test]
+  fn dependency_locations() {
+    let code = r#"
+    import {a} from './a';
+    function load() {
+      const b = require('./b');
+      return [a, b, process.cwd()];
+    }
+    "#;
+
+    let mut globals = config(code);
+    globals.insert_node_globals = true;
+    let result = transform(globals).unwrap();
+    let ranges = result
+      .dependencies
+      .iter()
+      .filter(|dep| !dep.is_helper)
+      .map(|dep| {
+        let specifier = dep.specifier_range.unwrap();
+        let statement = dep.statement_range.unwrap();
+        (
+          &*dep.specifier,
+          &code[specifier.start as usize..specifier.end as usize],
+          &code[statement.start as usize..statement.end as usize],
+        )
+      })
+      .collect::<Vec<_>>();
+    assert_eq!(
+      ranges,
+      vec![
+        ("./a", "'./a'", "import {a} from './a';"),
+        ("./b", "'./b'", "const b = require('./b');"),
+        ("process", "process", "return [a, b, process.cwd()];"),
+      ]
+    );
+
+    let dep = &result.dependencies[1];
+    assert_eq!(&*dep.specifier, "./a");
+    assert_eq!(dep.loc, dep.specifier_loc);
+    assert_eq!(
+      dep.statement_loc,
+      SourceLocation {
+        start_line: 2,
+        start_col: 5,
+        end_line: 2,
+        end_col: 26,
+      }
+    );
+  }
+}
@@ -11,7 +11,7 @@ use ast::*;
 
 pub struct EnvReplacer<'a> {
   pub replace_env: bool,
-  pub is_browser: bool,
+  pub context: EnvironmentContext,
   pub env: &'a HashMap<swc_atoms::JsWord, swc_atoms::JsWord>,
   pub decls: &'a HashSet<Id>,
   pub used_env: &'a mut HashSet<JsWord>,
@@ -28,7 +28,8 @@ impl<'a> Fold for EnvReplacer<'a> {
       if let PatOrExpr::Pat(ref pat) = assign.left {
         if let Pat::Expr(ref expr) = &**pat {
           if let Expr::Member(ref member) = &**expr {
-            if self.is_browser && match_member_expr(member, vec!["process", "browser"], self.decls)
+            if self.context.is_browser()
+              && match_member_expr(member, vec!["process", "browser"], self.decls)
             {
               let mut res = assign.clone();
               res.right = Box::new(Expr::Lit(Lit::Bool(Bool {
@@ -58,7 +59,9 @@ impl<'a> Fold for EnvReplacer<'a> {
     }
 
     if let Expr::Member(ref member) = node {
-      if self.is_browser && match_member_expr(member, vec!["process", "browser"], self.decls) {
+      if self.context.is_browser()
+        && match_member_expr(member, vec!["process", "browser"], self.decls)
+      {
         return Expr::Lit(Lit::Bool(Bool {
           value: true,
           span: DUMMY_SP,
@@ -1,4 +1,5 @@
 use crate::dependency_collector::{DependencyDescriptor, DependencyKind};
+use crate::fold_statement_span;
 use crate::hoist::{Collect, Import};
 use crate::id;
 use crate::utils::SourceLocation;
@@ -6,7 +7,7 @@ use data_encoding::{BASE64, HEXLOWER};
 use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 use swc_atoms::JsWord;
-use swc_common::{Mark, Span, DUMMY_SP};
+use swc_common::{Mark, Span, Spanned, DUMMY_SP};
 use swc_ecmascript::ast::*;
 use swc_ecmascript::visit::{Fold, FoldWith, VisitWith};
 
@@ -26,10 +27,12 @@ pub fn inline_fs<'a>(
       Mark::fresh(Mark::root()),
       global_mark,
       false,
+      HashSet::new(),
     ),
     global_mark,
     project_root,
     deps,
+    statement_span: DUMMY_SP,
   }
 }
 
@@ -39,6 +42,8 @@ struct InlineFS<'a> {
   global_mark: Mark,
   project_root: &'a str,
   deps: &'a mut Vec<DependencyDescriptor>,
+  /// The statement being folded, for dependency locations.
+  statement_span: Span,
 }
 
 impl<'a> Fold for InlineFS<'a> {
@@ -47,6 +52,8 @@ impl<'a> Fold for InlineFS<'a> {
     node.fold_children_with(self)
   }
 
+  fold_statement_span! {}
+
   fn fold_expr(&mut self, node: Expr) -> Expr {
     if let Expr::Call(call) = &node {
       if let Callee::Expr(expr) = &call.callee {
@@ -170,14 +177,14 @@ impl<'a> InlineFS<'a> {
 
         // Add a file dependency so the cache is invalidated when this file changes.
         self.deps.push(DependencyDescriptor {
-          kind: DependencyKind::File,
           loc: SourceLocation::from(&self.collect.source_map, span),
-          specifier: path.to_str().unwrap().into(),
-          attributes: None,
-          is_optional: false,
-          is_helper: false,
-          source_type: None,
-          placeholder: None,
+          ..DependencyDescriptor::new(
+            &self.collect.source_map,
+            DependencyKind::File,
+            path.to_str().unwrap().into(),
+            node.span(),
+            self.statement_span,
+          )
         });
 
         // If buffer, wrap in Buffer.from(base64String, 'base64')
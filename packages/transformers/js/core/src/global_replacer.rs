@@ -3,12 +3,13 @@ use std::collections::{HashMap, HashSet};
 use std::path::Path;
 
 use swc_atoms::JsWord;
-use swc_common::{Mark, SourceMap, SyntaxContext, DUMMY_SP};
+use swc_common::{Mark, SourceMap, Span, SyntaxContext, DUMMY_SP};
 use swc_ecmascript::ast::{self, ComputedPropName, Id};
 use swc_ecmascript::visit::{Fold, FoldWith};
 
 use crate::dependency_collector::{DependencyDescriptor, DependencyKind};
-use crate::utils::{create_global_decl_stmt, create_require, SourceLocation, SourceType};
+use crate::fold_statement_span;
+use crate::utils::{create_global_decl_stmt, create_require, SourceType};
 
 pub struct GlobalReplacer<'a> {
   pub source_map: &'a SourceMap,
@@ -19,9 +20,13 @@ pub struct GlobalReplacer<'a> {
   pub filename: &'a Path,
   pub decls: &'a mut HashSet<Id>,
   pub scope_hoist: bool,
+  /// The statement being folded, for dependency locations.
+  pub statement_span: Span,
 }
 
 impl<'a> Fold for GlobalReplacer<'a> {
+  fold_statement_span! {}
+
   fn fold_expr(&mut self, node: ast::Expr) -> ast::Expr {
     use ast::{Expr::*, Ident, MemberExpr, MemberProp};
 
@@ -53,16 +58,16 @@ impl<'a> Fold for GlobalReplacer<'a> {
       match id.sym.to_string().as_str() {
         "process" => {
           if self.update_binding(id, |_| Call(create_require(js_word!("process")))) {
-            let specifier = id.sym.clone();
             self.items.push(DependencyDescriptor {
-              kind: DependencyKind::Require,
-              loc: SourceLocation::from(self.source_map, id.span),
-              specifier,
-              attributes: None,
-              is_optional: false,
-              is_helper: false,
               source_type: Some(SourceType::Module),
-              placeholder: None,
+              imported_symbols: vec!["*".into()],
+              ..DependencyDescriptor::new(
+                self.source_map,
+                DependencyKind::Require,
+                id.sym.clone(),
+                id.span,
+                self.statement_span,
+              )
             });
           }
         }
@@ -76,14 +81,15 @@ impl<'a> Fold for GlobalReplacer<'a> {
             })
           }) {
             self.items.push(DependencyDescriptor {
-              kind: DependencyKind::Require,
-              loc: SourceLocation::from(self.source_map, id.span),
-              specifier,
-              attributes: None,
-              is_optional: false,
-              is_helper: false,
               source_type: Some(SourceType::Module),
-              placeholder: None,
+              imported_symbols: vec!["Buffer".into()],
+              ..DependencyDescriptor::new(
+                self.source_map,
+                DependencyKind::Require,
+                specifier,
+                id.span,
+                self.statement_span,
+              )
             });
           }
         }
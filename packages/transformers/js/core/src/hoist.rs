@@ -10,9 +10,10 @@ use swc_common::{sync::Lrc, Mark, Span, SyntaxContext, DUMMY_SP};
 use swc_ecmascript::ast::*;
 use swc_ecmascript::visit::{Fold, FoldWith, Visit, VisitWith};
 
+use crate::dependency_collector::DependencyKind;
 use crate::id;
 use crate::utils::{
-  match_import, match_member_expr, match_require, Bailout, BailoutReason, CodeHighlight,
+  is_marked, match_import, match_member_expr, match_require, Bailout, BailoutReason, CodeHighlight,
   Diagnostic, DiagnosticSeverity, SourceLocation,
 };
 
@@ -140,6 +141,32 @@ impl<'a> Fold for Hoist<'a> {
       match item {
         ModuleItem::ModuleDecl(decl) => {
           match decl {
+            // Imports of externals are kept as native imports.
+            ModuleDecl::Import(import) if is_marked(import.span, self.collect.ignore_mark) => {
+              // Wrapped assets are executed lazily inside a function, where imports are not allowed.
+              if self.collect.should_wrap {
+                self.diagnostics.push(Diagnostic {
+                  message: format!(
+                    "External \"{}\" cannot be imported from a module that is wrapped.",
+                    import.src.value
+                  ),
+                  code_highlights: Some(vec![CodeHighlight {
+                    loc: SourceLocation::from(&self.collect.source_map, import.src.span),
+                    message: None,
+                  }]),
+                  hints: Some(vec![String::from(
+                    "Use the \"require\" or \"global\" external type for this module instead.",
+                  )]),
+                  show_environment: false,
+                  severity: DiagnosticSeverity::Error,
+                  documentation_url: None,
+                });
+              }
+              let import = import.fold_with(self);
+              self
+                .hoisted_imports
+                .push(ModuleItem::ModuleDecl(ModuleDecl::Import(import)));
+            }
             ModuleDecl::Import(import) => {
               self
                 .hoisted_imports
@@ -1123,6 +1150,10 @@ pub struct Collect {
   pub non_static_requires: HashSet<JsWord>,
   pub wrapped_requires: HashSet<JsWord>,
   pub bailouts: Option<Vec<Bailout>>,
+  // placeholders of dynamic imports that were already compiled to require() calls
+  pub dynamic_imports: HashSet<JsWord>,
+  // statements that only consist of a require() or import() whose result is unused
+  unused_requires: Vec<SourceLocation>,
   in_module_this: bool,
   in_top_level: bool,
   in_export_decl: bool,
@@ -1167,6 +1198,7 @@ impl Collect {
     ignore_mark: Mark,
     global_mark: Mark,
     trace_bailouts: bool,
+    dynamic_imports: HashSet<JsWord>,
   ) -> Self {
     Collect {
       source_map,
@@ -1191,6 +1223,8 @@ impl Collect {
       in_function: false,
       in_assign: false,
       bailouts: if trace_bailouts { Some(vec![]) } else { None },
+      dynamic_imports,
+      unused_requires: vec![],
     }
   }
 }
@@ -1318,6 +1352,7 @@ impl Visit for Collect {
           Stmt::Expr(expr) => {
             // Top-level require(). Do not traverse further so it is not marked as wrapped.
             if let Some(_source) = self.match_require(&*expr.expr) {
+              self.add_unused_require(expr);
               return;
             }
 
@@ -1333,7 +1368,16 @@ impl Visit for Collect {
     self.in_top_level = true;
   }
 
+  fn visit_expr_stmt(&mut self, node: &ExprStmt) {
+    self.add_unused_require(node);
+    node.visit_children_with(self);
+  }
+
   fn visit_import_decl(&mut self, node: &ImportDecl) {
+    if is_marked(node.span, self.ignore_mark) {
+      return;
+    }
+
     for specifier in &node.specifiers {
       match specifier {
         ImportSpecifier::Named(named) => {
@@ -1722,7 +1766,7 @@ impl Visit for Collect {
       self.add_bailout(span, BailoutReason::NonTopLevelRequire);
     }
 
-    if let Some(source) = match_import(node, self.ignore_mark) {
+    if let Some(source) = self.match_import(node) {
       self.non_static_requires.insert(source.clone());
       self.wrapped_requires.insert(source);
       let span = match node {
@@ -1846,7 +1890,7 @@ impl Visit for Collect {
         Expr::Await(await_exp) => {
           // let x = await import('foo');
           // let {x} = await import('foo');
-          if let Some(source) = match_import(&*await_exp.arg, self.ignore_mark) {
+          if let Some(source) = self.match_import(&await_exp.arg) {
             self.add_pat_imports(&node.name, &source, ImportKind::DynamicImport);
             return;
           }
@@ -1874,7 +1918,7 @@ impl Visit for Collect {
         }
         Expr::Member(member) => {
           // import('foo').then(foo => ...);
-          if let Some(source) = match_import(&*member.obj, self.ignore_mark) {
+          if let Some(source) = self.match_import(&member.obj) {
             if match_property_name(member).map_or(false, |f| &*f.0 == "then") {
               if let Some(ExprOrSpread { expr, .. }) = node.args.get(0) {
                 let param = match &**expr {
@@ -1906,8 +1950,86 @@ impl Visit for Collect {
 }
 
 impl Collect {
+  /// Returns the names a dependency imports or re-exports, or `*` if all of them may be used.
+  /// Dependencies on the same source are told apart by their kind and the statement containing
+  /// them. An empty list means the dependency is only loaded for its side effects.
+  pub fn imported_symbols(
+    &self,
+    source: &JsWord,
+    kind: &DependencyKind,
+    statement: &SourceLocation,
+  ) -> Vec<JsWord> {
+    let mut symbols: Vec<JsWord> = match kind {
+      DependencyKind::Export => {
+        if matches!(self.exports_all.get(source), Some(loc) if statement.contains(loc)) {
+          return vec!["*".into()];
+        }
+
+        self
+          .exports
+          .values()
+          .filter(|export| {
+            export.source.as_ref() == Some(source) && statement.contains(&export.loc)
+          })
+          .map(|export| export.specifier.clone())
+          .collect()
+      }
+      DependencyKind::Import | DependencyKind::Require | DependencyKind::DynamicImport => self
+        .imports
+        .values()
+        .filter(|import| {
+          import.source == *source
+            && statement.contains(&import.loc)
+            && match import.kind {
+              ImportKind::Import => *kind == DependencyKind::Import,
+              // Dynamic imports may have been compiled to require() calls already.
+              ImportKind::Require | ImportKind::DynamicImport => *kind != DependencyKind::Import,
+            }
+        })
+        .map(|import| import.specifier.clone())
+        .collect(),
+      _ => return vec![],
+    };
+
+    // A require() or import() that is not destructured or accessed statically may use anything,
+    // unless its result is unused.
+    if symbols.is_empty()
+      && *kind != DependencyKind::Import
+      && *kind != DependencyKind::Export
+      && !self.unused_requires.contains(statement)
+    {
+      return vec!["*".into()];
+    }
+
+    if symbols.iter().any(|symbol| symbol == "*") {
+      return vec!["*".into()];
+    }
+
+    symbols.sort();
+    symbols.dedup();
+    symbols
+  }
+
+  fn add_unused_require(&mut self, node: &ExprStmt) {
+    if self.match_require(&node.expr).is_some() || self.match_import(&node.expr).is_some() {
+      self
+        .unused_requires
+        .push(SourceLocation::from(&self.source_map, node.span));
+    }
+  }
+
   pub fn match_require(&self, node: &Expr) -> Option<JsWord> {
     match_require(node, &self.decls, self.ignore_mark)
+      .filter(|source| !self.dynamic_imports.contains(source))
+  }
+
+  /// Matches `import('foo')`, including ones that were already compiled to `require()` calls
+  /// by the dependency collector when not scope hoisting.
+  pub fn match_import(&self, node: &Expr) -> Option<JsWord> {
+    match_import(node, self.ignore_mark).or_else(|| {
+      match_require(node, &self.decls, self.ignore_mark)
+        .filter(|source| self.dynamic_imports.contains(source))
+    })
   }
 
   fn add_pat_imports(&mut self, node: &Pat, src: &JsWord, kind: ImportKind) {
@@ -2118,7 +2240,9 @@ fn has_binding_identifier(node: &Pat, sym: &JsWord, decls: &HashSet<Id>) -> bool
 #[cfg(test)]
 mod tests {
   use super::*;
-  use crate::collect_decls;
+  use crate::test_utils::config;
+  use crate::utils::EnvironmentContext;
+  use crate::{collect_decls, transform, TransformResult};
   use std::iter::FromIterator;
   use swc_common::chain;
   use swc_common::comments::SingleThreadedComments;
@@ -2131,6 +2255,13 @@ mod tests {
   use self::indoc::indoc;
 
   fn parse(code: &str) -> (Collect, String, HoistResult) {
+    parse_with_dynamic_imports(code, HashSet::new())
+  }
+
+  fn parse_with_dynamic_imports(
+    code: &str,
+    dynamic_imports: HashSet<JsWord>,
+  ) -> (Collect, String, HoistResult) {
     let source_map = Lrc::new(SourceMap::default());
     let source_file = source_map.new_source_file(FileName::Anon, code.into());
 
@@ -2158,6 +2289,7 @@ mod tests {
               Mark::fresh(Mark::root()),
               global_mark,
               true,
+              dynamic_imports,
             );
             module.visit_with(&mut collect);
 
@@ -2741,6 +2873,63 @@ mod tests {
     assert_eq!(collect.wrapped_requires, set! {w!("other")});
   }
 
+  #[test]
+  fn compiled_dynamic_import() {
+    // Without scope hoisting, import() was already compiled to require() by the dependency collector.
+    let (collect, _code, _hoist) = parse_with_dynamic_imports(
+      r#"
+    require('other').then(({foo, bar: baz}) => foo + baz);
+    "#,
+      set! {w!("other")},
+    );
+    assert_eq_imports!(
+      collect.imports,
+      map! {
+        w!("foo") => (w!("other"), w!("foo"), true),
+        w!("baz") => (w!("other"), w!("bar"), true)
+      }
+    );
+    assert_eq!(collect.non_static_requires, set! {});
+    assert_eq!(collect.wrapped_requires, set! {w!("other")});
+
+    let (collect, _code, _hoist) = parse_with_dynamic_imports(
+      r#"
+    require('other').then(x => x.foo);
+    "#,
+      set! {w!("other")},
+    );
+    assert_eq_imports!(
+      collect.imports,
+      map! { w!("x") => (w!("other"), w!("*"), true) }
+    );
+    assert_eq_set!(collect.non_static_access.into_keys(), set! {});
+    assert_eq!(collect.non_static_requires, set! {});
+
+    let (collect, _code, _hoist) = parse_with_dynamic_imports(
+      r#"
+    async function test() {
+      const {foo} = await require('other');
+    }
+    "#,
+      set! {w!("other")},
+    );
+    assert_eq_imports!(
+      collect.imports,
+      map! { w!("foo") => (w!("other"), w!("foo"), true) }
+    );
+
+    let (collect, _code, _hoist) = parse_with_dynamic_imports(
+      r#"
+    const x = require('other');
+    "#,
+      set! {},
+    );
+    assert_eq_imports!(
+      collect.imports,
+      map! { w!("x") => (w!("other"), w!("*"), false) }
+    );
+  }
+
   #[test]
   fn fold_import() {
     let (collect, code, _hoist) = parse(
@@ -4252,4 +4441,143 @@ mod tests {
       }
     );
   }
+
+  #[test]
+  fn imported_symbols() {
+    let code = r#"
+    import React, {useState as useS} from 'react';
+    import * as utils from './utils';
+    export {a, b as c} from './reexport';
+    export * from './all';
+    const {x, y} = require('./cjs');
+    const {foo} = await import('./lazy');
+    import './side-effect';
+    console.log(React, useS, utils[name], x, y, foo);
+    "#;
+
+    let expected = vec![
+      ("react", vec!["default", "useState"]),
+      ("./utils", vec!["*"]),
+      ("./reexport", vec!["a", "b"]),
+      ("./all", vec!["*"]),
+      ("./cjs", vec!["x", "y"]),
+      ("./lazy", vec!["foo"]),
+      ("./side-effect", vec![]),
+    ];
+    let imported_symbols = |result: TransformResult| {
+      result
+        .dependencies
+        .into_iter()
+        .map(|dep| {
+          (
+            dep.specifier.to_string(),
+            dep
+              .imported_symbols
+              .iter()
+              .map(|symbol| symbol.to_string())
+              .collect::<Vec<_>>(),
+          )
+        })
+        .collect::<Vec<_>>()
+    };
+    let expected: Vec<_> = expected
+      .into_iter()
+      .map(|(specifier, symbols)| {
+        (
+          specifier.to_string(),
+          symbols.into_iter().map(String::from).collect::<Vec<_>>(),
+        )
+      })
+      .collect();
+
+    assert_eq!(imported_symbols(transform(config(code)).unwrap()), expected);
+
+    let mut hoisted = config(code);
+    hoisted.scope_hoist = true;
+    assert_eq!(imported_symbols(transform(hoisted).unwrap()), expected);
+
+    // Dependencies on the same source each get their own symbols.
+    let code = r#"
+    export {x} from './d';
+    import './d';
+    const foo = require('./e').foo;
+    console.log(require('./e'), Buffer);
+    "#;
+    let mut with_globals = config(code);
+    with_globals.insert_node_globals = true;
+    let result = transform(with_globals).unwrap();
+    assert_eq!(
+      result
+        .dependencies
+        .iter()
+        .map(|dep| (
+          &*dep.specifier,
+          dep
+            .imported_symbols
+            .iter()
+            .map(|symbol| &**symbol)
+            .collect::<Vec<_>>(),
+          dep.is_side_effect_only
+        ))
+        .collect::<Vec<_>>(),
+      vec![
+        // The declaration inserted for the global is collected too.
+        ("buffer", vec!["Buffer"], false),
+        ("./d", vec!["x"], false),
+        ("./d", vec![], true),
+        ("./e", vec!["foo"], false),
+        ("./e", vec!["*"], false),
+        // Injected dependencies use the names of the globals they replace.
+        ("buffer", vec!["Buffer"], false),
+      ]
+    );
+
+    let mut with_globals = config("console.log(process.nextTick);");
+    with_globals.insert_node_globals = true;
+    let result = transform(with_globals).unwrap();
+    assert_eq!(
+      result
+        .dependencies
+        .iter()
+        .map(|dep| (&*dep.specifier, dep.imported_symbols.clone()))
+        .collect::<Vec<_>>(),
+      vec![("process", vec!["*".into()]), ("process", vec!["*".into()])]
+    );
+
+    // Node replacements and inlined files.
+    let root = env!("CARGO_MANIFEST_DIR");
+    let mut node = config(
+      r#"
+    const fs = require('fs');
+    console.log(__dirname, fs.readFileSync(__dirname + '/../Cargo.toml', 'utf8'));
+    "#,
+    );
+    node.context = EnvironmentContext::Node;
+    node.inline_fs = true;
+    node.filename = format!("{}/src/lib.rs", root);
+    node.project_root = root.into();
+    let result = transform(node).unwrap();
+    assert_eq!(
+      result
+        .dependencies
+        .iter()
+        .map(|dep| (
+          &*dep.specifier,
+          dep
+            .imported_symbols
+            .iter()
+            .map(|symbol| &**symbol)
+            .collect::<Vec<_>>(),
+          dep.is_side_effect_only
+        ))
+        .collect::<Vec<_>>(),
+      vec![
+        // The call inserted for __dirname is not a static member access.
+        ("path", vec!["*"], false),
+        ("fs", vec!["*"], false),
+        ("path", vec!["resolve"], false),
+        (&*format!("{}/Cargo.toml", root), vec![], false),
+      ]
+    );
+  }
 }
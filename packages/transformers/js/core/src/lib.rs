@@ -17,6 +17,7 @@ mod env_replacer;
 mod fs;
 mod global_replacer;
 mod hoist;
+mod modularize_imports;
 mod modules;
 mod node_replacer;
 mod typeof_replacer;
@@ -30,11 +31,15 @@ use path_slash::PathExt;
 use serde::{Deserialize, Serialize};
 use swc_common::comments::SingleThreadedComments;
 use swc_common::errors::{DiagnosticBuilder, Emitter, Handler};
-use swc_common::{chain, sync::Lrc, FileName, Globals, Mark, SourceMap};
+use swc_common::{chain, sync::Lrc, FileName, Globals, Mark, SourceMap, DUMMY_SP};
 use swc_ecmascript::ast::Module;
 use swc_ecmascript::codegen::text_writer::JsWriter;
-use swc_ecmascript::parser::lexer::Lexer;
-use swc_ecmascript::parser::{EsConfig, PResult, Parser, StringInput, Syntax, TsConfig};
+use swc_ecmascript::parser::error::Error as ParserError;
+use swc_ecmascript::parser::lexer::{Lexer, TokenContexts};
+use swc_ecmascript::parser::token::{Keyword, Token, TokenAndSpan, Word};
+use swc_ecmascript::parser::{
+  Context, EsConfig, PResult, Parser, StringInput, Syntax, Tokens, TsConfig,
+};
 use swc_ecmascript::preset_env::{preset_env, Mode::Entry, Targets, Version, Versions};
 use swc_ecmascript::transforms::fixer::paren_remover;
 use swc_ecmascript::transforms::resolver;
@@ -51,10 +56,14 @@ use env_replacer::*;
 use fs::inline_fs;
 use global_replacer::GlobalReplacer;
 use hoist::{hoist, CollectResult, HoistResult};
+use modularize_imports::ModularizeImports;
 use modules::esm2cjs;
 use node_replacer::NodeReplacer;
 use typeof_replacer::*;
-use utils::{CodeHighlight, Diagnostic, DiagnosticSeverity, SourceLocation, SourceType};
+use utils::{
+  CodeHighlight, Diagnostic, DiagnosticSeverity, EnvironmentContext, External, ImportMap,
+  SourceLocation, SourceType,
+};
 
 use crate::hoist::Collect;
 
@@ -71,9 +80,7 @@ pub struct Config {
   env: HashMap<swc_atoms::JsWord, swc_atoms::JsWord>,
   inline_fs: bool,
   insert_node_globals: bool,
-  node_replacer: bool,
-  is_browser: bool,
-  is_worker: bool,
+  context: EnvironmentContext,
   is_type_script: bool,
   is_jsx: bool,
   jsx_pragma: Option<String>,
@@ -93,6 +100,12 @@ pub struct Config {
   is_esm_output: bool,
   trace_bailouts: bool,
   is_swc_helpers: bool,
+  externalize_remote_urls: bool,
+  protocols: HashMap<String, String>,
+  externals: HashMap<String, External>,
+  aliases: HashMap<String, String>,
+  import_map: Option<ImportMap>,
+  modularize_imports: HashMap<String, String>,
 }
 
 #[derive(Serialize, Debug, Default)]
@@ -110,7 +123,7 @@ pub struct TransformResult {
   has_node_replacements: bool,
 }
 
-fn targets_to_versions(targets: &Option<HashMap<String, String>>) -> Option<Versions> {
+pub(crate) fn targets_to_versions(targets: &Option<HashMap<String, String>>) -> Option<Versions> {
   if let Some(targets) = targets {
     macro_rules! set_target {
       ($versions: ident, $name: ident) => {
@@ -297,8 +310,14 @@ pub fn transform(config: Config) -> Result<TransformResult, std::io::Error> {
               let mut diagnostics = vec![];
               let module = {
                 let mut passes = chain!(
+                  // Rewrite named imports from barrel packages to per-member paths
+                  Optional::new(
+                    ModularizeImports::new(&config.modularize_imports),
+                    config.source_type != SourceType::Script
+                      && !config.modularize_imports.is_empty()
+                  ),
                   Optional::new(
-                    TypeofReplacer { decls: &decls },
+                    TypeofReplacer::new(&decls, config.context),
                     config.source_type != SourceType::Script
                   ),
                   // Inline process.env and process.browser
@@ -306,7 +325,7 @@ pub fn transform(config: Config) -> Result<TransformResult, std::io::Error> {
                     EnvReplacer {
                       replace_env: config.replace_env,
                       env: &config.env,
-                      is_browser: config.is_browser,
+                      context: config.context,
                       decls: &decls,
                       used_env: &mut result.used_env,
                       source_map: &source_map,
@@ -351,8 +370,10 @@ pub fn transform(config: Config) -> Result<TransformResult, std::io::Error> {
                     decls: &mut decls,
                     scope_hoist: config.scope_hoist,
                     has_node_replacements: &mut result.has_node_replacements,
+                    url_imports: HashMap::new(),
+                    statement_span: DUMMY_SP,
                   },
-                  config.node_replacer,
+                  config.context.is_node(),
                 ),
               );
 
@@ -368,9 +389,10 @@ pub fn transform(config: Config) -> Result<TransformResult, std::io::Error> {
                       project_root: Path::new(&config.project_root),
                       filename: Path::new(&config.filename),
                       decls: &mut decls,
-                      scope_hoist: config.scope_hoist
+                      scope_hoist: config.scope_hoist,
+                      statement_span: DUMMY_SP,
                     },
-                    config.insert_node_globals
+                    config.insert_node_globals && !config.context.is_node()
                   ),
                   // Transpile new syntax to older syntax if needed
                   Optional::new(
@@ -410,6 +432,7 @@ pub fn transform(config: Config) -> Result<TransformResult, std::io::Error> {
                 // Collect dependencies
                 &mut dependency_collector(
                   &source_map,
+                  &comments,
                   &mut result.dependencies,
                   &decls,
                   ignore_mark,
@@ -435,12 +458,35 @@ pub fn transform(config: Config) -> Result<TransformResult, std::io::Error> {
                 ignore_mark,
                 global_mark,
                 config.trace_bailouts,
+                result
+                  .dependencies
+                  .iter()
+                  .filter(|dep| dep.kind == DependencyKind::DynamicImport)
+                  .filter_map(|dep| dep.placeholder.as_deref().map(|p| p.into()))
+                  .collect(),
               );
               module.visit_with(&mut collect);
               if let Some(bailouts) = &collect.bailouts {
                 diagnostics.extend(bailouts.iter().map(|bailout| bailout.to_diagnostic()));
               }
 
+              // Record the names imported by each dependency. The code refers to dependencies
+              // by their placeholder if they have one. Dependencies injected by the node and
+              // global replacers already know the names used by the global they replace.
+              for dep in result.dependencies.iter_mut().chain(fs_deps.iter_mut()) {
+                let source = dep.placeholder.as_deref().unwrap_or(&dep.specifier).into();
+                dep.imported_symbols =
+                  collect.imported_symbols(&source, &dep.kind, &dep.statement_loc);
+                dep.is_side_effect_only = dep.imported_symbols.is_empty()
+                  && matches!(
+                    dep.kind,
+                    DependencyKind::Import
+                      | DependencyKind::Export
+                      | DependencyKind::Require
+                      | DependencyKind::DynamicImport
+                  );
+              }
+
               let module = if config.scope_hoist {
                 let res = hoist(module, config.module_id.as_str(), unresolved_mark, &collect);
                 match res {
@@ -526,6 +572,7 @@ fn parse(
       jsx: config.is_jsx,
       export_default_from: true,
       decorators: config.decorators,
+      import_assertions: true,
       ..Default::default()
     })
   };
@@ -537,13 +584,93 @@ fn parse(
     Some(&comments),
   );
 
-  let mut parser = Parser::new_from(lexer);
+  let mut parser = Parser::new_from(ImportAttributesLexer {
+    lexer,
+    prev_is_str: false,
+  });
   match parser.parse_module() {
     Err(err) => Err(err),
     Ok(module) => Ok((module, comments)),
   }
 }
 
+/// Wraps the lexer so that import attributes can be written with `with`, e.g.
+/// `import data from './a.json' with {type: 'json'}`. The parser only understands
+/// the legacy `assert` keyword, so a `with` token directly following the module
+/// specifier string is passed on as `assert`, keeping its original span.
+#[derive(Clone)]
+struct ImportAttributesLexer<'a> {
+  lexer: Lexer<'a, StringInput<'a>>,
+  prev_is_str: bool,
+}
+
+impl<'a> Iterator for ImportAttributesLexer<'a> {
+  type Item = TokenAndSpan;
+
+  fn next(&mut self) -> Option<TokenAndSpan> {
+    let mut token = self.lexer.next()?;
+    // A string followed by `with` on the same line is never valid elsewhere.
+    if self.prev_is_str
+      && !token.had_line_break
+      && token.token == Token::Word(Word::Keyword(Keyword::With))
+    {
+      token.token = Token::Word(Word::Ident("assert".into()));
+    }
+    self.prev_is_str = matches!(token.token, Token::Str { .. });
+    Some(token)
+  }
+}
+
+impl<'a> Tokens for ImportAttributesLexer<'a> {
+  fn set_ctx(&mut self, ctx: Context) {
+    self.lexer.set_ctx(ctx)
+  }
+
+  fn ctx(&self) -> Context {
+    self.lexer.ctx()
+  }
+
+  fn syntax(&self) -> Syntax {
+    self.lexer.syntax()
+  }
+
+  fn target(&self) -> swc_ecmascript::ast::EsVersion {
+    self.lexer.target()
+  }
+
+  fn start_pos(&self) -> swc_common::BytePos {
+    self.lexer.start_pos()
+  }
+
+  fn set_expr_allowed(&mut self, allow: bool) {
+    self.lexer.set_expr_allowed(allow)
+  }
+
+  fn token_context(&self) -> &TokenContexts {
+    self.lexer.token_context()
+  }
+
+  fn token_context_mut(&mut self) -> &mut TokenContexts {
+    self.lexer.token_context_mut()
+  }
+
+  fn set_token_context(&mut self, c: TokenContexts) {
+    self.lexer.set_token_context(c)
+  }
+
+  fn add_error(&self, error: ParserError) {
+    self.lexer.add_error(error)
+  }
+
+  fn add_module_mode_error(&self, error: ParserError) {
+    self.lexer.add_module_mode_error(error)
+  }
+
+  fn take_errors(&mut self) -> Vec<ParserError> {
+    self.lexer.take_errors()
+  }
+}
+
 fn emit(
   source_map: Lrc<SourceMap>,
   comments: SingleThreadedComments,
@@ -629,3 +756,64 @@ fn error_buffer_to_diagnostics(
     })
     .collect()
 }
+
+#[cfg(test)]
+pub(crate) mod test_utils {
+  use super::*;
+
+  /// A config for `/app/src/index.js` in the browser, with everything else disabled.
+  pub fn config(code: &str) -> Config {
+    Config {
+      filename: "/app/src/index.js".into(),
+      code: code.into(),
+      module_id: "abc".into(),
+      project_root: "/app".into(),
+      replace_env: false,
+      env: HashMap::new(),
+      inline_fs: false,
+      insert_node_globals: false,
+      context: EnvironmentContext::Browser,
+      is_type_script: false,
+      is_jsx: false,
+      jsx_pragma: None,
+      jsx_pragma_frag: None,
+      automatic_jsx_runtime: false,
+      jsx_import_source: None,
+      decorators: false,
+      use_define_for_class_fields: false,
+      is_development: false,
+      react_refresh: false,
+      targets: None,
+      source_maps: false,
+      scope_hoist: false,
+      source_type: SourceType::Module,
+      supports_module_workers: false,
+      is_library: false,
+      is_esm_output: false,
+      trace_bailouts: false,
+      is_swc_helpers: false,
+      externalize_remote_urls: false,
+      protocols: HashMap::new(),
+      externals: HashMap::new(),
+      aliases: HashMap::new(),
+      import_map: None,
+      modularize_imports: HashMap::new(),
+    }
+  }
+
+  pub fn specifiers(result: &TransformResult) -> Vec<&str> {
+    result
+      .dependencies
+      .iter()
+      .map(|dep| &*dep.specifier)
+      .collect()
+  }
+
+  pub fn dependencies(result: &TransformResult) -> Vec<String> {
+    result
+      .dependencies
+      .iter()
+      .map(|dep| format!("{} {}", dep.kind, dep.specifier))
+      .collect()
+  }
+}
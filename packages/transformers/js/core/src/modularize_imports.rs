@@ -0,0 +1,293 @@
+use std::collections::HashMap;
+
+use inflector::Inflector;
+use swc_atoms::{js_word, JsWord};
+use swc_common::DUMMY_SP;
+use swc_ecmascript::ast::*;
+use swc_ecmascript::visit::Fold;
+
+use crate::utils::match_export_name;
+
+/// Rewrites named imports from barrel packages into default imports of per-member paths,
+/// so each member becomes its own dependency rather than the whole package.
+///
+/// import {map, debounce as d} from 'lodash';
+///   => import map from 'lodash/map'; import d from 'lodash/debounce';
+///
+/// Templates refer to the imported name as `{{member}}`, optionally with a casing
+/// helper, e.g. `@mui/material/{{ pascalCase member }}`.
+pub struct ModularizeImports<'a> {
+  packages: &'a HashMap<String, String>,
+}
+
+impl<'a> ModularizeImports<'a> {
+  pub fn new(packages: &'a HashMap<String, String>) -> Self {
+    ModularizeImports { packages }
+  }
+
+  fn rewrite_import(&self, import: ImportDecl, template: &str) -> Vec<ModuleItem> {
+    let mut items = vec![];
+    let mut remaining = vec![];
+    for specifier in import.specifiers {
+      match specifier {
+        ImportSpecifier::Named(named) if !named.is_type_only => {
+          let member = match &named.imported {
+            Some(imported) => match_export_name(imported).0,
+            None => named.local.sym.clone(),
+          };
+          if member == js_word!("default") {
+            remaining.push(ImportSpecifier::Named(named));
+            continue;
+          }
+
+          items.push(ModuleItem::ModuleDecl(ModuleDecl::Import(ImportDecl {
+            span: import.span,
+            specifiers: vec![ImportSpecifier::Default(ImportDefaultSpecifier {
+              span: named.span,
+              local: named.local,
+            })],
+            src: Str {
+              span: import.src.span,
+              value: render_template(template, &member).into(),
+              raw: None,
+            },
+            type_only: false,
+            asserts: import.asserts.clone(),
+          })));
+        }
+        specifier => remaining.push(specifier),
+      }
+    }
+
+    // Keep default and namespace imports of the package itself.
+    if !remaining.is_empty() || items.is_empty() {
+      items.insert(
+        0,
+        ModuleItem::ModuleDecl(ModuleDecl::Import(ImportDecl {
+          specifiers: remaining,
+          ..import
+        })),
+      );
+    }
+
+    items
+  }
+
+  fn rewrite_export(&self, export: NamedExport, src: Str, template: &str) -> Vec<ModuleItem> {
+    let mut items = vec![];
+    let mut remaining = vec![];
+    for specifier in export.specifiers {
+      match specifier {
+        ExportSpecifier::Named(named)
+          if !named.is_type_only && *match_export_name(&named.orig).0 != js_word!("default") =>
+        {
+          let member = match_export_name(&named.orig).0;
+          let exported = named.exported.unwrap_or(named.orig);
+          items.push(ModuleItem::ModuleDecl(ModuleDecl::ExportNamed(
+            NamedExport {
+              span: export.span,
+              specifiers: vec![ExportSpecifier::Named(ExportNamedSpecifier {
+                span: named.span,
+                orig: ModuleExportName::Ident(Ident::new(js_word!("default"), DUMMY_SP)),
+                exported: Some(exported),
+                is_type_only: false,
+              })],
+              src: Some(Str {
+                span: src.span,
+                value: render_template(template, &member).into(),
+                raw: None,
+              }),
+              type_only: false,
+              asserts: export.asserts.clone(),
+            },
+          )));
+        }
+        specifier => remaining.push(specifier),
+      }
+    }
+
+    if !remaining.is_empty() {
+      items.insert(
+        0,
+        ModuleItem::ModuleDecl(ModuleDecl::ExportNamed(NamedExport {
+          specifiers: remaining,
+          src: Some(src),
+          ..export
+        })),
+      );
+    }
+
+    items
+  }
+}
+
+impl<'a> Fold for ModularizeImports<'a> {
+  fn fold_module_items(&mut self, items: Vec<ModuleItem>) -> Vec<ModuleItem> {
+    let mut res = Vec::with_capacity(items.len());
+    for item in items {
+      match item {
+        ModuleItem::ModuleDecl(ModuleDecl::Import(import)) if !import.type_only => {
+          match self.packages.get(&*import.src.value) {
+            Some(template) => res.extend(self.rewrite_import(import, template)),
+            None => res.push(ModuleItem::ModuleDecl(ModuleDecl::Import(import))),
+          }
+        }
+        ModuleItem::ModuleDecl(ModuleDecl::ExportNamed(NamedExport {
+          span,
+          specifiers,
+          src: Some(src),
+          type_only: false,
+          asserts,
+        }))
+          if self.packages.contains_key(&*src.value) =>
+        {
+          let template = &self.packages[&*src.value];
+          let export = NamedExport {
+            span,
+            specifiers,
+            src: None,
+            type_only: false,
+            asserts,
+          };
+          res.extend(self.rewrite_export(export, src, template));
+        }
+        item => res.push(item),
+      }
+    }
+
+    res
+  }
+
+  fn fold_module(&mut self, node: Module) -> Module {
+    // Imports and exports can only appear at the top level.
+    Module {
+      body: self.fold_module_items(node.body),
+      ..node
+    }
+  }
+}
+
+/// Renders a path template, replacing `{{member}}` or `{{ helper member }}` with the
+/// imported name. Supported helpers are `camelCase`, `pascalCase`, `kebabCase`,
+/// `snakeCase`, `upperCase` and `lowerCase`.
+fn render_template(template: &str, member: &JsWord) -> String {
+  let mut res = String::with_capacity(template.len());
+  let mut rest = template;
+  while let Some(start) = rest.find("{{") {
+    let end = match rest[start..].find("}}") {
+      Some(end) => start + end,
+      None => break,
+    };
+    res.push_str(&rest[..start]);
+
+    let mut parts = rest[start + 2..end].split_whitespace();
+    let rendered = match (parts.next(), parts.next()) {
+      (Some("member"), None) => member.to_string(),
+      (Some(helper), Some("member")) => match helper {
+        "camelCase" => member.to_camel_case(),
+        "pascalCase" => member.to_pascal_case(),
+        "kebabCase" => member.to_kebab_case(),
+        "snakeCase" => member.to_snake_case(),
+        "upperCase" => member.to_uppercase(),
+        "lowerCase" => member.to_lowercase(),
+        _ => member.to_string(),
+      },
+      _ => rest[start..end + 2].to_string(),
+    };
+    res.push_str(&rendered);
+    rest = &rest[end + 2..];
+  }
+  res.push_str(rest);
+  res
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use swc_common::{sync::Lrc, FileName, SourceMap};
+  use swc_ecmascript::codegen::text_writer::JsWriter;
+  use swc_ecmascript::parser::lexer::Lexer;
+  use swc_ecmascript::parser::{Parser, StringInput};
+  use swc_ecmascript::visit::FoldWith;
+  extern crate indoc;
+  use self::indoc::indoc;
+
+  fn modularize(code: &str, packages: &HashMap<String, String>) -> String {
+    let source_map = Lrc::new(SourceMap::default());
+    let source_file = source_map.new_source_file(FileName::Anon, code.into());
+    let lexer = Lexer::new(
+      Default::default(),
+      Default::default(),
+      StringInput::from(&*source_file),
+      None,
+    );
+    let module = Parser::new_from(lexer).parse_module().unwrap();
+    let module = module.fold_with(&mut ModularizeImports::new(packages));
+
+    let mut buf = vec![];
+    {
+      let writer = Box::new(JsWriter::new(source_map.clone(), "\n", &mut buf, None));
+      let mut emitter = swc_ecmascript::codegen::Emitter {
+        cfg: Default::default(),
+        comments: None,
+        cm: source_map,
+        wr: writer,
+      };
+      emitter.emit_module(&module).unwrap();
+    }
+    String::from_utf8(buf).unwrap()
+  }
+
+  #[test]
+  fn modularize_imports() {
+    let packages = HashMap::from([
+      ("lodash".into(), "lodash/{{member}}".into()),
+      (
+        "@mui/material".into(),
+        "@mui/material/{{ pascalCase member }}".into(),
+      ),
+      (
+        "swr-utils".into(),
+        "swr-utils/{{ kebabCase member }}".into(),
+      ),
+    ]);
+
+    let code = modularize(
+      indoc! {r#"
+    import _, {map, debounce as d} from 'lodash';
+    import {Button, TextField} from '@mui/material';
+    import {Component} from 'react';
+    export {useQuery, default as swr} from 'swr-utils';
+    "#},
+      &packages,
+    );
+    assert_eq!(
+      code,
+      indoc! {r#"
+    import _ from 'lodash';
+    import map from "lodash/map";
+    import d from "lodash/debounce";
+    import Button from "@mui/material/Button";
+    import TextField from "@mui/material/TextField";
+    import { Component } from 'react';
+    export { default as swr } from 'swr-utils';
+    export { default as useQuery } from "swr-utils/use-query";
+    "#}
+    );
+  }
+
+  #[test]
+  fn render_templates() {
+    let member = JsWord::from("useQuery");
+    assert_eq!(render_template("pkg/{{member}}", &member), "pkg/useQuery");
+    assert_eq!(
+      render_template("pkg/{{ snakeCase member }}", &member),
+      "pkg/use_query"
+    );
+    assert_eq!(
+      render_template("pkg/{{ upperCase member }}", &member),
+      "pkg/USEQUERY"
+    );
+    assert_eq!(render_template("pkg/{{other}}", &member), "pkg/{{other}}");
+  }
+}
@@ -95,7 +95,9 @@ impl ESMFold {
       decls: vec![VarDeclarator {
         span: DUMMY_SP,
         name: Pat::Ident(ident.into()),
-        init: Some(Box::new(Expr::Call(crate::utils::create_require(src)))),
+        init: Some(Box::new(Expr::Call(crate::utils::create_require(
+          crate::utils::normalize_node_specifier(src, self.versions),
+        )))),
         definite: false,
       }],
       declare: false,
@@ -2,13 +2,17 @@ use std::collections::{HashMap, HashSet};
 use std::ffi::OsStr;
 use std::path::Path;
 
-use swc_atoms::JsWord;
-use swc_common::{Mark, SourceMap, SyntaxContext, DUMMY_SP};
+use swc_atoms::{js_word, JsWord};
+use swc_common::{Mark, SourceMap, Span, SyntaxContext, DUMMY_SP};
 use swc_ecmascript::ast::{self, Id};
 use swc_ecmascript::visit::{Fold, FoldWith};
 
 use crate::dependency_collector::{DependencyDescriptor, DependencyKind};
-use crate::utils::{create_global_decl_stmt, create_require, SourceLocation, SourceType};
+use crate::fold_statement_span;
+use crate::utils::{
+  create_global_decl_stmt, create_require, is_import_meta, match_export_name, match_property_name,
+  SourceType,
+};
 
 pub struct NodeReplacer<'a> {
   pub source_map: &'a SourceMap,
@@ -20,12 +24,39 @@ pub struct NodeReplacer<'a> {
   pub decls: &'a mut HashSet<Id>,
   pub scope_hoist: bool,
   pub has_node_replacements: &'a mut bool,
+  /// Local bindings imported from `url`, mapped to the imported name (`*` for namespaces).
+  pub url_imports: HashMap<Id, JsWord>,
+  /// The statement being folded, for dependency locations.
+  pub statement_span: Span,
 }
 
 impl<'a> Fold for NodeReplacer<'a> {
+  fold_statement_span! {}
+
   fn fold_expr(&mut self, node: ast::Expr) -> ast::Expr {
     use ast::{Expr::*, MemberExpr, MemberProp};
 
+    // Treat the ESM equivalents the same way as their CommonJS counterparts.
+    // import.meta.dirname -> __dirname
+    // import.meta.filename, fileURLToPath(import.meta.url) -> __filename
+    let node = match self.match_import_meta_path(&node) {
+      // If the module declares its own top level binding with the same name, e.g.
+      // `const __filename = fileURLToPath(import.meta.url)`, inline the path
+      // rather than declaring a global that would clash with it.
+      Some((name, span))
+        if !self.globals.contains_key(&JsWord::from(name))
+          && self.decls.contains(&(
+            name.into(),
+            SyntaxContext::empty().apply_mark(self.global_mark),
+          )) =>
+      {
+        self.add_path_dependency(span);
+        return self.create_path_expr(name);
+      }
+      Some((name, span)) => Ident(ast::Ident::new(name.into(), span)),
+      None => node,
+    };
+
     // Do not traverse into the `prop` side of member expressions unless computed.
     let mut node = match node {
       Member(expr) => {
@@ -52,114 +83,14 @@ impl<'a> Fold for NodeReplacer<'a> {
       }
 
       match id.sym.to_string().as_str() {
-        "__filename" => {
-          let specifier = swc_atoms::JsWord::from("path");
-          let replace_me_value = swc_atoms::JsWord::from("$parcel$filenameReplace");
-
-          let expr = |this: &NodeReplacer| {
-            let filename = if let Some(name) = this.filename.file_name() {
-              name
-            } else {
-              OsStr::new("unknown.js")
-            };
-            ast::Expr::Call(ast::CallExpr {
-              span: DUMMY_SP,
-              type_args: None,
-              args: vec![
-                ast::ExprOrSpread {
-                  spread: None,
-                  expr: Box::new(ast::Expr::Ident(ast::Ident {
-                    optional: false,
-                    span: DUMMY_SP,
-                    // This also uses __dirname as later in the path.join call the hierarchy is then correct
-                    // Otherwise path.join(__filename, '..') would be one level to shallow (due to the /filename.js at the end)
-                    sym: swc_atoms::JsWord::from("__dirname"),
-                  })),
-                },
-                ast::ExprOrSpread {
-                  spread: None,
-                  expr: Box::new(ast::Expr::Lit(ast::Lit::Str(ast::Str {
-                    span: DUMMY_SP,
-                    value: replace_me_value,
-                    raw: None,
-                  }))),
-                },
-                ast::ExprOrSpread {
-                  spread: None,
-                  expr: Box::new(ast::Expr::Lit(ast::Lit::Str(ast::Str {
-                    span: DUMMY_SP,
-                    value: swc_atoms::JsWord::from(filename.to_string_lossy()),
-                    raw: None,
-                  }))),
-                },
-              ],
-              callee: ast::Callee::Expr(Box::new(ast::Expr::Member(ast::MemberExpr {
-                span: DUMMY_SP,
-                obj: (Box::new(Call(create_require(specifier.clone())))),
-                prop: MemberProp::Ident(ast::Ident::new("resolve".into(), DUMMY_SP)),
-              }))),
-            })
+        "__filename" | "__dirname" => {
+          let name = if &*id.sym == "__filename" {
+            "__filename"
+          } else {
+            "__dirname"
           };
-          if self.update_binding(id, expr) {
-            self.items.push(DependencyDescriptor {
-              kind: DependencyKind::Require,
-              loc: SourceLocation::from(self.source_map, id.span),
-              specifier,
-              attributes: None,
-              is_optional: false,
-              is_helper: false,
-              source_type: Some(SourceType::Module),
-              placeholder: None,
-            });
-
-            *self.has_node_replacements = true;
-          }
-        }
-        "__dirname" => {
-          let specifier = swc_atoms::JsWord::from("path");
-          let replace_me_value = swc_atoms::JsWord::from("$parcel$dirnameReplace");
-
-          if self.update_binding(id, |_| {
-            ast::Expr::Call(ast::CallExpr {
-              span: DUMMY_SP,
-              type_args: None,
-              args: vec![
-                ast::ExprOrSpread {
-                  spread: None,
-                  expr: Box::new(ast::Expr::Ident(ast::Ident {
-                    optional: false,
-                    span: DUMMY_SP,
-                    sym: swc_atoms::JsWord::from("__dirname"),
-                  })),
-                },
-                ast::ExprOrSpread {
-                  spread: None,
-                  expr: Box::new(ast::Expr::Lit(ast::Lit::Str(ast::Str {
-                    span: DUMMY_SP,
-                    value: replace_me_value,
-                    raw: None,
-                  }))),
-                },
-              ],
-              callee: ast::Callee::Expr(Box::new(ast::Expr::Member(ast::MemberExpr {
-                span: DUMMY_SP,
-                obj: (Box::new(Call(create_require(specifier.clone())))),
-                prop: MemberProp::Ident(ast::Ident::new("resolve".into(), DUMMY_SP)),
-              }))),
-            })
-          }) {
-            self.items.push(DependencyDescriptor {
-              kind: DependencyKind::Require,
-              loc: SourceLocation::from(self.source_map, id.span),
-              specifier,
-              attributes: None,
-              is_optional: false,
-              is_helper: false,
-              source_type: Some(SourceType::Module),
-              placeholder: None,
-            });
-
-            *self.has_node_replacements = true;
+          if self.update_binding(id, |this| this.create_path_expr(name)) {
+            self.add_path_dependency(id.span);
           }
         }
         _ => {}
@@ -170,6 +101,27 @@ impl<'a> Fold for NodeReplacer<'a> {
   }
 
   fn fold_module(&mut self, node: ast::Module) -> ast::Module {
+    for item in &node.body {
+      if let ast::ModuleItem::ModuleDecl(ast::ModuleDecl::Import(import)) = item {
+        if import.src.value == *"url" || import.src.value == *"node:url" {
+          for specifier in &import.specifiers {
+            let (local, imported) = match specifier {
+              ast::ImportSpecifier::Named(named) => (
+                &named.local,
+                match &named.imported {
+                  Some(imported) => match_export_name(imported).0,
+                  None => named.local.sym.clone(),
+                },
+              ),
+              ast::ImportSpecifier::Default(default) => (&default.local, "*".into()),
+              ast::ImportSpecifier::Namespace(namespace) => (&namespace.local, "*".into()),
+            };
+            self.url_imports.insert(local.to_id(), imported);
+          }
+        }
+      }
+    }
+
     // Insert globals at the top of the program
     let mut node = swc_ecmascript::visit::fold_module(self, node);
     node.body.splice(
@@ -184,6 +136,123 @@ impl<'a> Fold for NodeReplacer<'a> {
 }
 
 impl NodeReplacer<'_> {
+  fn match_import_meta_path(&self, node: &ast::Expr) -> Option<(&'static str, Span)> {
+    match node {
+      ast::Expr::Member(member) if is_import_meta(&member.obj) => {
+        match match_property_name(member) {
+          Some((name, _)) if &*name == "dirname" => Some(("__dirname", member.span)),
+          Some((name, _)) if &*name == "filename" => Some(("__filename", member.span)),
+          _ => None,
+        }
+      }
+      ast::Expr::Call(call) => {
+        let is_file_url_to_path = match &call.callee {
+          ast::Callee::Expr(callee) => match &**callee {
+            ast::Expr::Ident(ident) => {
+              matches!(self.url_imports.get(&ident.to_id()), Some(imported) if imported == "fileURLToPath")
+            }
+            ast::Expr::Member(member) => match &*member.obj {
+              ast::Expr::Ident(ident) => {
+                matches!(self.url_imports.get(&ident.to_id()), Some(imported) if imported == "*")
+                  && matches!(match_property_name(member), Some((name, _)) if &*name == "fileURLToPath")
+              }
+              _ => false,
+            },
+            _ => false,
+          },
+          _ => false,
+        };
+
+        if !is_file_url_to_path || call.args.len() != 1 {
+          return None;
+        }
+
+        match &*call.args[0].expr {
+          ast::Expr::Member(member)
+            if is_import_meta(&member.obj)
+              && matches!(match_property_name(member), Some((name, _)) if name == js_word!("url")) =>
+          {
+            Some(("__filename", call.span))
+          }
+          _ => None,
+        }
+      }
+      _ => None,
+    }
+  }
+
+  /// Creates an expression resolving `__filename` or `__dirname` relative to the output bundle.
+  /// The placeholder is replaced by the packager.
+  fn create_path_expr(&self, name: &str) -> ast::Expr {
+    let mut args = vec![
+      ast::ExprOrSpread {
+        spread: None,
+        expr: Box::new(ast::Expr::Ident(ast::Ident {
+          optional: false,
+          span: DUMMY_SP,
+          // This also uses __dirname as later in the path.join call the hierarchy is then correct
+          // Otherwise path.join(__filename, '..') would be one level to shallow (due to the /filename.js at the end)
+          sym: swc_atoms::JsWord::from("__dirname"),
+        })),
+      },
+      ast::ExprOrSpread {
+        spread: None,
+        expr: Box::new(ast::Expr::Lit(ast::Lit::Str(ast::Str {
+          span: DUMMY_SP,
+          value: if name == "__filename" {
+            "$parcel$filenameReplace".into()
+          } else {
+            "$parcel$dirnameReplace".into()
+          },
+          raw: None,
+        }))),
+      },
+    ];
+
+    if name == "__filename" {
+      let filename = if let Some(name) = self.filename.file_name() {
+        name
+      } else {
+        OsStr::new("unknown.js")
+      };
+      args.push(ast::ExprOrSpread {
+        spread: None,
+        expr: Box::new(ast::Expr::Lit(ast::Lit::Str(ast::Str {
+          span: DUMMY_SP,
+          value: swc_atoms::JsWord::from(filename.to_string_lossy()),
+          raw: None,
+        }))),
+      });
+    }
+
+    ast::Expr::Call(ast::CallExpr {
+      span: DUMMY_SP,
+      type_args: None,
+      args,
+      callee: ast::Callee::Expr(Box::new(ast::Expr::Member(ast::MemberExpr {
+        span: DUMMY_SP,
+        obj: Box::new(ast::Expr::Call(create_require("path".into()))),
+        prop: ast::MemberProp::Ident(ast::Ident::new("resolve".into(), DUMMY_SP)),
+      }))),
+    })
+  }
+
+  fn add_path_dependency(&mut self, span: Span) {
+    self.items.push(DependencyDescriptor {
+      source_type: Some(SourceType::Module),
+      imported_symbols: vec!["resolve".into()],
+      ..DependencyDescriptor::new(
+        self.source_map,
+        DependencyKind::Require,
+        "path".into(),
+        span,
+        self.statement_span,
+      )
+    });
+
+    *self.has_node_replacements = true;
+  }
+
   fn update_binding<F>(&mut self, id: &mut ast::Ident, expr: F) -> bool
   where
     F: FnOnce(&Self) -> ast::Expr,
@@ -203,3 +272,57 @@ impl NodeReplacer<'_> {
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use crate::test_utils::config;
+  use crate::transform;
+  use crate::utils::EnvironmentContext;
+
+  #[test]
+  fn node_import_meta_paths() {
+    let mut node = config(
+      r#"
+    import {fileURLToPath} from 'node:url';
+    import * as url from 'url';
+    const fileURLToPath2 = () => {};
+    console.log(import.meta.dirname, import.meta.filename);
+    console.log(fileURLToPath(import.meta.url), url.fileURLToPath(import.meta.url));
+    console.log(fileURLToPath2(import.meta.url));
+    "#,
+    );
+    node.context = EnvironmentContext::Node;
+    let result = transform(node).unwrap();
+    assert!(result.has_node_replacements);
+    let code = String::from_utf8(result.code).unwrap();
+    assert!(!code.contains("import.meta"));
+    assert!(!code.contains("fileURLToPath(__filename)"));
+    assert!(code.contains("console.log(__filename, __filename);"));
+    assert!(code.contains("fileURLToPath2(\"file:///src/index.js\")"));
+    assert_eq!(code.matches("$parcel$dirnameReplace").count(), 1);
+    assert_eq!(code.matches("$parcel$filenameReplace").count(), 1);
+
+    // The common ESM idiom declares its own __filename and __dirname bindings.
+    let mut node = config(
+      r#"
+    import {fileURLToPath} from 'url';
+    import path from 'path';
+    const __filename = fileURLToPath(import.meta.url);
+    const __dirname = path.dirname(__filename);
+    console.log(import.meta.dirname, __filename, __dirname);
+    "#,
+    );
+    node.context = EnvironmentContext::Node;
+    let result = transform(node).unwrap();
+    assert!(result.has_node_replacements);
+    let code = String::from_utf8(result.code).unwrap();
+    assert!(!code.contains("var __filename"));
+    assert!(!code.contains("var __dirname"));
+    assert!(code.contains(
+      "const __filename = require(\"path\").resolve(__dirname, \"$parcel$filenameReplace\", \"index.js\");"
+    ));
+    assert!(code.contains(
+      "console.log(require(\"path\").resolve(__dirname, \"$parcel$dirnameReplace\"), __filename"
+    ));
+  }
+}
@@ -1,17 +1,123 @@
 use std::collections::HashSet;
 
 use swc_atoms::js_word;
-use swc_ecmascript::ast::{Expr, Id, Lit, Str, UnaryOp};
-use swc_ecmascript::visit::{Fold, FoldWith};
+use swc_common::Spanned;
+use swc_ecmascript::ast::{
+  BinExpr, BinaryOp, Bool, Expr, Id, IfStmt, Lit, Str, UnaryExpr, UnaryOp,
+};
+use swc_ecmascript::visit::{Fold, FoldWith, Visit, VisitWith};
 
 use crate::id;
+use crate::utils::{match_member_expr, match_str, EnvironmentContext};
 
 pub struct TypeofReplacer<'a> {
-  pub decls: &'a HashSet<Id>,
+  decls: &'a HashSet<Id>,
+  context: EnvironmentContext,
+}
+
+impl<'a> TypeofReplacer<'a> {
+  pub fn new(decls: &'a HashSet<Id>, context: EnvironmentContext) -> Self {
+    TypeofReplacer { decls, context }
+  }
+
+  /// Matches the AMD check of a UMD wrapper, e.g. `typeof define === 'function' && define.amd`.
+  fn is_amd_check(&self, node: &Expr) -> bool {
+    let mut operands = vec![];
+    flatten_and(node, &mut operands);
+
+    let mut has_typeof = false;
+    let mut has_amd = false;
+    for operand in operands {
+      match operand {
+        Expr::Bin(BinExpr {
+          op: BinaryOp::EqEq | BinaryOp::EqEqEq,
+          left,
+          right,
+          ..
+        }) => match (&**left, &**right) {
+          (Expr::Unary(unary), other) | (other, Expr::Unary(unary))
+            if self.is_typeof_define(unary)
+              && matches!(match_str(other), Some((value, _)) if &*value == "function") =>
+          {
+            has_typeof = true
+          }
+          _ => return false,
+        },
+        Expr::Member(member) if match_member_expr(member, vec!["define", "amd"], self.decls) => {
+          has_amd = true
+        }
+        _ => return false,
+      }
+    }
+
+    has_typeof && has_amd
+  }
+
+  fn is_typeof_define(&self, node: &UnaryExpr) -> bool {
+    match &*node.arg {
+      Expr::Ident(ident) => {
+        node.op == UnaryOp::TypeOf && &*ident.sym == "define" && !self.decls.contains(&id!(ident))
+      }
+      _ => false,
+    }
+  }
+
+  /// UMD wrappers check for AMD before CommonJS. Since define calls are compiled to CommonJS
+  /// anyway, the AMD check is false when the wrapper has a CommonJS fallback so that dependencies
+  /// are not loaded twice. Wrappers without one keep the AMD branch, which the dependency
+  /// collector compiles.
+  fn fold_amd_check<N>(&self, test: Box<Expr>, alt: Option<&N>) -> Box<Expr>
+  where
+    N: for<'b> VisitWith<CommonJsDetector<'b>>,
+  {
+    if !self.is_amd_check(&test) {
+      return test;
+    }
+
+    let mut detector = CommonJsDetector {
+      decls: self.decls,
+      found: false,
+    };
+    if let Some(alt) = alt {
+      alt.visit_with(&mut detector);
+    }
+
+    Box::new(Expr::Lit(Lit::Bool(Bool {
+      span: test.span(),
+      value: !detector.found,
+    })))
+  }
+}
+
+fn flatten_and<'a>(node: &'a Expr, operands: &mut Vec<&'a Expr>) {
+  match node {
+    Expr::Bin(BinExpr {
+      op: BinaryOp::LogicalAnd,
+      left,
+      right,
+      ..
+    }) => {
+      flatten_and(left, operands);
+      flatten_and(right, operands);
+    }
+    Expr::Paren(paren) => flatten_and(&paren.expr, operands),
+    _ => operands.push(node),
+  }
 }
 
 impl<'a> Fold for TypeofReplacer<'a> {
+  fn fold_if_stmt(&mut self, mut node: IfStmt) -> IfStmt {
+    node.test = self.fold_amd_check(node.test, node.alt.as_deref());
+    node.fold_children_with(self)
+  }
+
   fn fold_expr(&mut self, node: Expr) -> Expr {
+    // typeof define === 'function' && define.amd ? define(factory) : ...
+    if let Expr::Cond(mut cond) = node {
+      cond.test = self.fold_amd_check(cond.test, Some(&*cond.alt));
+      return Expr::Cond(cond.fold_children_with(self));
+    }
+
     if let Expr::Unary(ref unary) = node {
       // typeof require -> "function"
       // typeof module -> "object"
@@ -39,9 +145,161 @@ impl<'a> Fold for TypeofReplacer<'a> {
               raw: None,
             }));
           }
+
+          // typeof window -> "object" in browsers, "undefined" in workers and node
+          if !self.decls.contains(&id!(ident)) {
+            if let Some(value) = self.context.typeof_global(&ident.sym) {
+              return Expr::Lit(Lit::Str(Str {
+                span: unary.span,
+                value: value.into(),
+                raw: None,
+              }));
+            }
+          }
         }
       }
     }
     node.fold_children_with(self)
   }
 }
+
+/// Finds a `typeof exports` or `typeof module` check in the fallback of a UMD wrapper.
+struct CommonJsDetector<'a> {
+  decls: &'a HashSet<Id>,
+  found: bool,
+}
+
+impl<'a> Visit for CommonJsDetector<'a> {
+  fn visit_unary_expr(&mut self, node: &UnaryExpr) {
+    if node.op == UnaryOp::TypeOf {
+      if let Expr::Ident(ident) = &*node.arg {
+        if matches!(&*ident.sym, "exports" | "module") && !self.decls.contains(&id!(ident)) {
+          self.found = true;
+        }
+      }
+    }
+    node.visit_children_with(self);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_utils::{config, specifiers};
+  use crate::transform;
+
+  #[test]
+  fn typeof_globals() {
+    let code = r#"
+    if (typeof window !== 'undefined') {
+      require('./browser');
+    } else {
+      require('./node');
+    }
+    if (typeof process === 'object') {
+      require('./process');
+    }
+    "#;
+
+    let result = transform(config(code)).unwrap();
+    assert_eq!(specifiers(&result), vec!["./browser", "./process"]);
+
+    let mut worker = config(code);
+    worker.context = EnvironmentContext::WebWorker;
+    let result = transform(worker).unwrap();
+    assert_eq!(specifiers(&result), vec!["./node", "./process"]);
+
+    let mut node = config(code);
+    node.context = EnvironmentContext::Node;
+    let result = transform(node).unwrap();
+    assert_eq!(specifiers(&result), vec!["./node", "./process"]);
+
+    // Bound identifiers are not replaced.
+    let result = transform(config(
+      r#"
+    const window = undefined;
+    if (typeof window === 'undefined') {
+      require('./a');
+    }
+    "#,
+    ))
+    .unwrap();
+    assert_eq!(specifiers(&result), vec!["./a"]);
+  }
+
+  #[test]
+  fn umd_wrapper() {
+    // The CommonJS branch is chosen, so dependencies are not loaded twice.
+    let result = transform(config(
+      r#"
+    (function (root, factory) {
+      if (typeof define === 'function' && define.amd) {
+        define(['./dep'], factory);
+      } else if (typeof exports === 'object') {
+        module.exports = factory(require('./dep'));
+      } else {
+        root.lib = factory(root.dep);
+      }
+    })(this, function (dep) {});
+    "#,
+    ))
+    .unwrap();
+    assert_eq!(specifiers(&result), vec!["./dep"]);
+    let code = std::str::from_utf8(&result.code).unwrap();
+    assert!(!code.contains("define"));
+
+    // Wrappers with only an AMD branch keep it.
+    let result = transform(config(
+      r#"
+    (function (root, factory) {
+      if (typeof define === 'function' && define.amd) {
+        define(['./dep'], factory);
+      } else {
+        root.lib = factory(root.dep);
+      }
+    })(this, function (dep) {});
+    "#,
+    ))
+    .unwrap();
+    assert_eq!(specifiers(&result), vec!["./dep"]);
+    let code = std::str::from_utf8(&result.code).unwrap();
+    assert!(!code.contains("root.lib"));
+
+    // Conditional expression wrappers are recognized too.
+    let result = transform(config(
+      r#"
+    (function (root, factory) {
+      typeof define === 'function' && define.amd ? define(['./dep'], factory) :
+        typeof exports === 'object' ? module.exports = factory(require('./dep')) :
+        root.lib = factory(root.dep);
+    })(this, function (dep) {});
+    "#,
+    ))
+    .unwrap();
+    assert_eq!(specifiers(&result), vec!["./dep"]);
+    let code = std::str::from_utf8(&result.code).unwrap();
+    assert!(!code.contains("define"));
+
+    // Other typeof define checks are left alone, even next to a wrapper.
+    let result = transform(config(
+      r#"
+    if (typeof define === 'function') {
+      require('./a');
+    } else {
+      require('./b');
+    }
+    (function (root, factory) {
+      if (typeof define === 'function' && define.amd) {
+        define(['./dep'], factory);
+      } else if (typeof exports === 'object') {
+        module.exports = factory(require('./dep'));
+      }
+    })(this, function (dep) {});
+    "#,
+    ))
+    .unwrap();
+    assert_eq!(specifiers(&result), vec!["./a", "./b", "./dep"]);
+    let code = std::str::from_utf8(&result.code).unwrap();
+    assert!(code.contains("typeof define"));
+  }
+}
@@ -1,11 +1,12 @@
 use std::cmp::Ordering;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use crate::id;
 use serde::{Deserialize, Serialize};
 use swc_atoms::JsWord;
 use swc_common::{Mark, Span, SyntaxContext, DUMMY_SP};
 use swc_ecmascript::ast::{self, Id};
+use swc_ecmascript::preset_env::Versions;
 
 pub fn match_member_expr(expr: &ast::MemberExpr, idents: Vec<&str>, decls: &HashSet<Id>) -> bool {
   use ast::{Expr, Ident, Lit, MemberProp, Str};
@@ -43,18 +44,13 @@ pub fn match_member_expr(expr: &ast::MemberExpr, idents: Vec<&str>, decls: &Hash
 }
 
 pub fn create_require(specifier: swc_atoms::JsWord) -> ast::CallExpr {
-  let mut normalized_specifier = specifier;
-  if normalized_specifier.starts_with("node:") {
-    normalized_specifier = normalized_specifier.replace("node:", "").into();
-  }
-
   ast::CallExpr {
     callee: ast::Callee::Expr(Box::new(ast::Expr::Ident(ast::Ident::new(
       "require".into(),
       DUMMY_SP,
     )))),
     args: vec![ast::ExprOrSpread {
-      expr: Box::new(ast::Expr::Lit(ast::Lit::Str(normalized_specifier.into()))),
+      expr: Box::new(ast::Expr::Lit(ast::Lit::Str(specifier.into()))),
       spread: None,
     }],
     span: DUMMY_SP,
@@ -62,7 +58,26 @@ pub fn create_require(specifier: swc_atoms::JsWord) -> ast::CallExpr {
   }
 }
 
-fn is_marked(span: Span, mark: Mark) -> bool {
+/// Removes the `node:` prefix from a builtin module specifier, unless the target
+/// supports requiring prefixed builtins (Node 14.18+).
+pub fn normalize_node_specifier(specifier: JsWord, versions: Option<Versions>) -> JsWord {
+  match specifier.strip_prefix("node:") {
+    Some(name) if !supports_node_prefix(versions) => name.into(),
+    _ => specifier,
+  }
+}
+
+fn supports_node_prefix(versions: Option<Versions>) -> bool {
+  // The node: prefix was backported to 14.18, but is missing from 15.x.
+  match versions {
+    Some(Versions {
+      node: Some(node), ..
+    }) => node.major >= 16 || (node.major == 14 && node.minor >= 18),
+    _ => false,
+  }
+}
+
+pub fn is_marked(span: Span, mark: Mark) -> bool {
   let mut ctxt = span.ctxt();
 
   loop {
@@ -114,6 +129,16 @@ pub fn match_export_name_ident(name: &ast::ModuleExportName) -> &ast::Ident {
   }
 }
 
+pub fn is_import_meta(node: &ast::Expr) -> bool {
+  matches!(
+    node,
+    ast::Expr::MetaProp(ast::MetaPropExpr {
+      kind: ast::MetaPropKind::ImportMeta,
+      ..
+    })
+  )
+}
+
 pub fn match_require(node: &ast::Expr, decls: &HashSet<Id>, ignore_mark: Mark) -> Option<JsWord> {
   use ast::*;
 
@@ -205,6 +230,12 @@ pub struct SourceLocation {
 }
 
 impl SourceLocation {
+  /// Whether this location fully contains another one.
+  pub fn contains(&self, other: &SourceLocation) -> bool {
+    (self.start_line, self.start_col) <= (other.start_line, other.start_col)
+      && (other.end_line, other.end_col) <= (self.end_line, self.end_col)
+  }
+
   pub fn from(source_map: &swc_common::SourceMap, span: swc_common::Span) -> Self {
     if span.lo.is_dummy() || span.hi.is_dummy() {
       return SourceLocation {
@@ -229,6 +260,26 @@ impl SourceLocation {
   }
 }
 
+/// Byte offsets of a span within the source code.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Eq, PartialEq)]
+pub struct ByteRange {
+  pub start: u32,
+  pub end: u32,
+}
+
+impl ByteRange {
+  pub fn from(source_map: &swc_common::SourceMap, span: swc_common::Span) -> Option<Self> {
+    if span.lo.is_dummy() || span.hi.is_dummy() {
+      return None;
+    }
+
+    Some(ByteRange {
+      start: source_map.lookup_byte_offset(span.lo).pos.0,
+      end: source_map.lookup_byte_offset(span.hi).pos.0,
+    })
+  }
+}
+
 impl PartialOrd for SourceLocation {
   fn partial_cmp(&self, other: &SourceLocation) -> Option<Ordering> {
     match self.start_line.cmp(&other.start_line) {
@@ -270,6 +321,190 @@ pub enum SourceType {
   Module,
 }
 
+/// The environment that the compiled code will run in.
+#[derive(Serialize, Debug, Deserialize, Eq, PartialEq, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum EnvironmentContext {
+  Browser,
+  WebWorker,
+  ServiceWorker,
+  Worklet,
+  WebExtension,
+  ElectronMain,
+  ElectronRenderer,
+  Node,
+  Deno,
+}
+
+impl EnvironmentContext {
+  /// Whether the code runs in a browser engine, where `process.browser` is true.
+  pub fn is_browser(&self) -> bool {
+    use EnvironmentContext::*;
+    matches!(
+      self,
+      Browser | WebWorker | ServiceWorker | Worklet | WebExtension | ElectronRenderer
+    )
+  }
+
+  /// Whether the code runs in a classic or module worker that supports `importScripts`.
+  pub fn is_worker(&self) -> bool {
+    matches!(
+      self,
+      EnvironmentContext::WebWorker | EnvironmentContext::ServiceWorker
+    )
+  }
+
+  /// Whether `__dirname`, `__filename` and Node builtins are available natively.
+  /// Electron renderers are both browser and Node environments.
+  pub fn is_node(&self) -> bool {
+    use EnvironmentContext::*;
+    matches!(self, Node | ElectronMain | ElectronRenderer)
+  }
+
+  /// Whether `new Worker(...)` starts a web worker. Worklets can't create workers.
+  pub fn supports_web_workers(&self) -> bool {
+    use EnvironmentContext::*;
+    (self.is_browser() && *self != Worklet) || *self == Deno
+  }
+
+  /// Returns the result of `typeof name` for an unbound global, if it is known statically.
+  pub fn typeof_global(&self, name: &str) -> Option<&'static str> {
+    use EnvironmentContext::*;
+    match (self, name) {
+      (Browser | ElectronRenderer, "window" | "document" | "self") => Some("object"),
+      (WebWorker | ServiceWorker, "window" | "document") => Some("undefined"),
+      (WebWorker | ServiceWorker | WebExtension | Deno, "self") => Some("object"),
+      (Worklet, "window" | "document") => Some("undefined"),
+      (Deno, "document") => Some("undefined"),
+      (Node | ElectronMain, "window" | "document" | "self") => Some("undefined"),
+      (Node | ElectronMain, "process") => Some("object"),
+      // Whether `process` exists in browsers depends on the GlobalReplacer, and
+      // in Electron renderers on `nodeIntegration`, so those are left alone.
+      _ => None,
+    }
+  }
+}
+
+/// How an external dependency is referenced in the output rather than bundled.
+#[derive(Serialize, Debug, Deserialize, Eq, PartialEq, Clone)]
+#[serde(rename_all = "lowercase")]
+pub enum External {
+  /// Keep a `require` call.
+  Require,
+  /// Keep an `import` declaration or `import()` call.
+  Import,
+  /// Reference a global variable, e.g. a library loaded from a CDN.
+  Global(String),
+}
+
+/// Returns how a specifier should be externalized. Patterns are exact specifiers,
+/// prefixes ending with `/`, or globs containing `*` or `?`. The longest matching pattern wins.
+pub fn match_external<'a>(
+  externals: &'a HashMap<String, External>,
+  specifier: &str,
+) -> Option<&'a External> {
+  if let Some(external) = externals.get(specifier) {
+    return Some(external);
+  }
+
+  externals
+    .iter()
+    .filter(|(pattern, _)| {
+      if pattern.contains('*') || pattern.contains('?') {
+        glob_match(pattern.as_bytes(), specifier.as_bytes())
+      } else {
+        pattern.ends_with('/') && specifier.starts_with(pattern.as_str())
+      }
+    })
+    .max_by_key(|(pattern, _)| pattern.len())
+    .map(|(_, external)| external)
+}
+
+/// Matches a glob where `*` matches within a path segment, and `**` across segments.
+fn glob_match(pattern: &[u8], value: &[u8]) -> bool {
+  match pattern.split_first() {
+    None => value.is_empty(),
+    Some((b'*', rest)) => {
+      if let Some(rest) = rest.strip_prefix(b"*") {
+        (0..=value.len()).any(|i| glob_match(rest, &value[i..]))
+      } else {
+        (0..=value.len())
+          .take_while(|&i| i == 0 || value[i - 1] != b'/')
+          .any(|i| glob_match(rest, &value[i..]))
+      }
+    }
+    Some((b'?', rest)) => {
+      matches!(value.split_first(), Some((c, value)) if *c != b'/' && glob_match(rest, value))
+    }
+    Some((c, rest)) => {
+      matches!(value.split_first(), Some((v, value)) if v == c && glob_match(rest, value))
+    }
+  }
+}
+
+/// A [WICG import map](https://github.com/WICG/import-maps).
+#[derive(Serialize, Debug, Deserialize, Default)]
+pub struct ImportMap {
+  #[serde(default)]
+  pub imports: HashMap<String, String>,
+  /// Imports that only apply to modules whose path starts with the scope.
+  #[serde(default)]
+  pub scopes: HashMap<String, HashMap<String, String>>,
+}
+
+impl ImportMap {
+  /// Resolves a specifier imported by a module at `referrer`, a path relative to the project root.
+  /// More specific scopes take precedence over less specific ones and the top-level imports.
+  pub fn resolve(&self, specifier: &str, referrer: &str) -> Option<String> {
+    let mut scopes: Vec<_> = self
+      .scopes
+      .iter()
+      .filter(|(scope, _)| referrer.starts_with(scope.as_str()))
+      .collect();
+    scopes.sort_by_key(|(scope, _)| std::cmp::Reverse(scope.len()));
+    scopes
+      .into_iter()
+      .map(|(_, imports)| imports)
+      .chain(std::iter::once(&self.imports))
+      .find_map(|imports| match_import_map(imports, specifier))
+  }
+}
+
+/// Rewrites a specifier using an alias table. Keys match exactly, or as a prefix followed
+/// by a path, e.g. `react` matches `react/jsx-runtime`. The longest matching key wins.
+/// Relative values are resolved from the project root.
+pub fn match_alias(aliases: &HashMap<String, String>, specifier: &str) -> Option<String> {
+  map_specifier(aliases, specifier, |key| {
+    key.ends_with('/') || specifier[key.len()..].starts_with('/')
+  })
+}
+
+/// Like `match_alias`, but follows import map semantics: only keys ending with `/`
+/// match as a prefix, so `react` does not match `react/jsx-runtime`.
+fn match_import_map(imports: &HashMap<String, String>, specifier: &str) -> Option<String> {
+  map_specifier(imports, specifier, |key| key.ends_with('/'))
+}
+
+fn map_specifier<F: Fn(&str) -> bool>(
+  aliases: &HashMap<String, String>,
+  specifier: &str,
+  is_prefix: F,
+) -> Option<String> {
+  let (key, value) = match aliases.get_key_value(specifier) {
+    Some(entry) => entry,
+    None => aliases
+      .iter()
+      .filter(|(key, _)| specifier.starts_with(key.as_str()) && is_prefix(key))
+      .max_by_key(|(key, _)| key.len())?,
+  };
+
+  let mapped = format!("{}{}", value, &specifier[key.len()..]);
+  match mapped.strip_prefix("./") {
+    Some(path) => Some(format!("/{}", path)),
+    None => Some(mapped),
+  }
+}
+
 #[derive(Debug)]
 pub struct Bailout {
   pub loc: SourceLocation,
@@ -359,6 +594,32 @@ impl BailoutReason {
   }
 }
 
+/// Keeps the span of the statement or module declaration being folded in `self.statement_span`,
+/// so dependencies can record the statement containing them.
+#[macro_export]
+macro_rules! fold_statement_span {
+  () => {
+    fn fold_stmt(&mut self, node: swc_ecmascript::ast::Stmt) -> swc_ecmascript::ast::Stmt {
+      let span = swc_common::Spanned::span(&node);
+      let statement_span = std::mem::replace(&mut self.statement_span, span);
+      let res = node.fold_children_with(self);
+      self.statement_span = statement_span;
+      res
+    }
+
+    fn fold_module_decl(
+      &mut self,
+      node: swc_ecmascript::ast::ModuleDecl,
+    ) -> swc_ecmascript::ast::ModuleDecl {
+      let span = swc_common::Spanned::span(&node);
+      let statement_span = std::mem::replace(&mut self.statement_span, span);
+      let res = node.fold_children_with(self);
+      self.statement_span = statement_span;
+      res
+    }
+  };
+}
+
 #[macro_export]
 macro_rules! fold_member_expr_skip_prop {
   () => {
@@ -383,3 +644,151 @@ macro_rules! id {
     $ident.to_id()
   };
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_utils::{config, dependencies};
+  use crate::transform;
+
+  #[test]
+  fn environment_contexts() {
+    use EnvironmentContext::*;
+
+    let code = r#"
+    if (typeof window !== 'undefined') require('./window');
+    if (typeof self === 'undefined') require('./no-self');
+    if (process.browser) require('./process-browser');
+    if (typeof process === 'object') require('./process');
+    new Worker(new URL('./worker.js', import.meta.url));
+    importScripts('./script.js');
+    __dirname;
+    "#;
+
+    // (context, dependencies, has importScripts error, has node replacements)
+    let expected: Vec<(EnvironmentContext, Vec<&str>, bool, bool)> = vec![
+      (
+        Browser,
+        vec![
+          "Require ./window",
+          "Require ./process-browser",
+          "Require ./process",
+          "WebWorker ./worker.js",
+        ],
+        false,
+        false,
+      ),
+      (
+        WebWorker,
+        vec![
+          "Require ./process-browser",
+          "Require ./process",
+          "WebWorker ./worker.js",
+        ],
+        true,
+        false,
+      ),
+      (
+        ServiceWorker,
+        vec![
+          "Require ./process-browser",
+          "Require ./process",
+          "WebWorker ./worker.js",
+        ],
+        true,
+        false,
+      ),
+      (
+        Worklet,
+        vec![
+          "Require ./no-self",
+          "Require ./process-browser",
+          "Require ./process",
+          "Url ./worker.js",
+        ],
+        false,
+        false,
+      ),
+      (
+        WebExtension,
+        vec![
+          "Require ./window",
+          "Require ./process-browser",
+          "Require ./process",
+          "WebWorker ./worker.js",
+        ],
+        false,
+        false,
+      ),
+      (
+        ElectronRenderer,
+        vec![
+          "Require path",
+          "Require ./window",
+          "Require ./process-browser",
+          "Require ./process",
+          "WebWorker ./worker.js",
+          "Require path",
+        ],
+        false,
+        true,
+      ),
+      (
+        ElectronMain,
+        vec![
+          "Require path",
+          "Require ./no-self",
+          "Require ./process-browser",
+          "Require ./process",
+          "Url ./worker.js",
+          "Require path",
+        ],
+        false,
+        true,
+      ),
+      (
+        Node,
+        vec![
+          "Require path",
+          "Require ./no-self",
+          "Require ./process-browser",
+          "Require ./process",
+          "Url ./worker.js",
+          "Require path",
+        ],
+        false,
+        true,
+      ),
+      (
+        Deno,
+        vec![
+          "Require ./window",
+          "Require ./process-browser",
+          "Require ./process",
+          "WebWorker ./worker.js",
+        ],
+        false,
+        false,
+      ),
+    ];
+
+    for (context, deps, has_import_scripts_error, has_node_replacements) in expected {
+      let mut config = config(code);
+      config.context = context;
+      let result = transform(config).unwrap();
+      let has_error = result
+        .diagnostics
+        .iter()
+        .flatten()
+        .any(|d| d.message.contains("importScripts"));
+
+      assert_eq!(dependencies(&result), deps, "{:?}", context);
+      assert_eq!(has_error, has_import_scripts_error, "{:?}", context);
+      assert_eq!(
+        result.has_node_replacements, has_node_replacements,
+        "{:?}",
+        context
+      );
+    }
+  }
+}
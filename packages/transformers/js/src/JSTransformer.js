@@ -85,6 +85,72 @@ const CONFIG_SCHEMA: SchemaEntity = {
     inlineFS: {
       type: 'boolean',
     },
+    externalizeRemoteURLs: {
+      type: 'boolean',
+    },
+    inlineDataURLs: {
+      type: 'boolean',
+    },
+    protocols: {
+      type: 'object',
+      additionalProperties: {
+        type: 'string',
+      },
+    },
+    aliases: {
+      type: 'object',
+      additionalProperties: {
+        type: 'string',
+      },
+    },
+    importMap: {
+      type: 'object',
+      properties: {
+        imports: {
+          type: 'object',
+          additionalProperties: {
+            type: 'string',
+          },
+        },
+        scopes: {
+          type: 'object',
+          additionalProperties: {
+            type: 'object',
+            additionalProperties: {
+              type: 'string',
+            },
+          },
+        },
+      },
+      additionalProperties: false,
+    },
+    modularizeImports: {
+      type: 'object',
+      additionalProperties: {
+        type: 'string',
+      },
+    },
+    externals: {
+      type: 'object',
+      additionalProperties: {
+        oneOf: [
+          {
+            type: 'string',
+            enum: ['require', 'import'],
+          },
+          {
+            type: 'object',
+            properties: {
+              global: {
+                type: 'string',
+              },
+            },
+            required: ['global'],
+            additionalProperties: false,
+          },
+        ],
+      },
+    },
     inlineEnvironment: {
       oneOf: [
         {
@@ -106,9 +172,27 @@ type PackageJSONConfig = {|
   '@parcel/transformer-js'?: {|
     inlineFS?: boolean,
     inlineEnvironment?: boolean | Array<string>,
+    externalizeRemoteURLs?: boolean,
+    inlineDataURLs?: boolean,
+    protocols?: {[protocol: string]: string},
+    aliases?: {[specifier: string]: string},
+    importMap?: {|
+      imports?: {[specifier: string]: string},
+      scopes?: {[scope: string]: {[specifier: string]: string}},
+    |},
+    modularizeImports?: {[pkg: string]: string},
+    externals?: {[specifier: string]: 'require' | 'import' | {|global: string|}},
   |},
 |};
 
+// Types of assets that can be created from data URL dependencies.
+const DATA_URL_TYPES = {
+  'text/javascript': 'js',
+  'application/javascript': 'js',
+  'application/json': 'json',
+  'text/css': 'css',
+};
+
 const SCRIPT_ERRORS = {
   browser: {
     message: 'Browser scripts cannot have imports or exports.',
@@ -253,6 +337,13 @@ export default (new Transformer({
 
     let inlineEnvironment = config.isSource;
     let inlineFS = !ignoreFS;
+    let externalizeRemoteURLs = false;
+    let inlineDataURLs = false;
+    let protocols = {};
+    let externals = {};
+    let aliases = {};
+    let importMap = null;
+    let modularizeImports = {};
     if (result && rootPkg?.['@parcel/transformer-js']) {
       validateSchema.diagnostic(
         CONFIG_SCHEMA,
@@ -272,6 +363,18 @@ export default (new Transformer({
         rootPkg['@parcel/transformer-js']?.inlineEnvironment ??
         inlineEnvironment;
       inlineFS = rootPkg['@parcel/transformer-js']?.inlineFS ?? inlineFS;
+      externalizeRemoteURLs =
+        rootPkg['@parcel/transformer-js']?.externalizeRemoteURLs ??
+        externalizeRemoteURLs;
+      inlineDataURLs =
+        rootPkg['@parcel/transformer-js']?.inlineDataURLs ?? inlineDataURLs;
+      protocols = rootPkg['@parcel/transformer-js']?.protocols ?? protocols;
+      externals = rootPkg['@parcel/transformer-js']?.externals ?? externals;
+      aliases = rootPkg['@parcel/transformer-js']?.aliases ?? aliases;
+      importMap = rootPkg['@parcel/transformer-js']?.importMap ?? importMap;
+      modularizeImports =
+        rootPkg['@parcel/transformer-js']?.modularizeImports ??
+        modularizeImports;
     }
 
     return {
@@ -282,6 +385,13 @@ export default (new Transformer({
       pragmaFrag,
       inlineEnvironment,
       inlineFS,
+      externalizeRemoteURLs,
+      inlineDataURLs,
+      protocols,
+      externals,
+      aliases,
+      importMap,
+      modularizeImports,
       reactRefresh,
       decorators,
       useDefineForClassFields,
@@ -394,9 +504,7 @@ export default (new Transformer({
       inline_fs: Boolean(config?.inlineFS) && !asset.env.isNode(),
       insert_node_globals:
         !asset.env.isNode() && asset.env.sourceType !== 'script',
-      node_replacer: asset.env.isNode(),
-      is_browser: asset.env.isBrowser(),
-      is_worker: asset.env.isWorker(),
+      context: asset.env.context,
       env,
       is_type_script: asset.type === 'ts' || asset.type === 'tsx',
       is_jsx: isJSX,
@@ -423,6 +531,12 @@ export default (new Transformer({
       is_esm_output: asset.env.outputFormat === 'esmodule',
       trace_bailouts: options.logLevel === 'verbose',
       is_swc_helpers: /@swc[/\\]helpers/.test(asset.filePath),
+      externalize_remote_urls: Boolean(config?.externalizeRemoteURLs),
+      protocols: config?.protocols ?? {},
+      externals: config?.externals ?? {},
+      aliases: config?.aliases ?? {},
+      import_map: config?.importMap ?? null,
+      modularize_imports: config?.modularizeImports ?? {},
     });
 
     let convertLoc = loc => {
@@ -534,6 +648,7 @@ export default (new Transformer({
       asset.invalidateOnEnvChange(env);
     }
 
+    let inlineAssets = [];
     for (let dep of dependencies) {
       if (dep.kind === 'WebWorker') {
         // Use native ES module output if the worker was created with `type: 'module'` and all targets
@@ -590,6 +705,37 @@ export default (new Transformer({
             outputFormat: 'esmodule', // Worklets require ESM
             loc,
           },
+          meta: {
+            placeholder: dep.placeholder,
+            workletType: dep.worklet_type,
+          },
+        });
+      } else if (dep.kind === 'ImportScripts') {
+        // Scripts loaded by classic workers share the worker's global scope.
+        let loc = convertLoc(dep.loc);
+        asset.addURLDependency(dep.specifier, {
+          loc,
+          env: {
+            sourceType: 'script',
+            outputFormat: 'global',
+            loc,
+          },
+          meta: {
+            placeholder: dep.placeholder,
+          },
+        });
+      } else if (dep.kind === 'NodeWorker' || dep.kind === 'ChildProcess') {
+        // Node workers and child processes are separate entries in the same environment.
+        let loc = convertLoc(dep.loc);
+        asset.addURLDependency(dep.specifier, {
+          loc,
+          env: {
+            context: 'node',
+            sourceType: 'module',
+            outputFormat:
+              asset.env.outputFormat === 'esmodule' ? 'esmodule' : 'commonjs',
+            loc,
+          },
           meta: {
             placeholder: dep.placeholder,
           },
@@ -607,6 +753,7 @@ export default (new Transformer({
       } else {
         let meta: JSONObject = {kind: dep.kind};
         if (dep.attributes) {
+          // Attribute values are always strings, e.g. {type: 'json'}.
           meta.importAttributes = dep.attributes;
         }
 
@@ -614,6 +761,55 @@ export default (new Transformer({
           meta.placeholder = dep.placeholder;
         }
 
+        if (dep.bundle_name) {
+          meta.bundleName = dep.bundle_name;
+        }
+
+        if (dep.resource_hint) {
+          meta.resourceHint = dep.resource_hint;
+        }
+
+        // External dependencies are excluded by the resolver and loaded at runtime.
+        if (dep.is_external) {
+          meta.isExternal = true;
+        }
+
+        // Keep track of explicit `node:` builtins, since the prefix may be stripped from the code.
+        if (dep.is_builtin) {
+          meta.isBuiltin = true;
+        }
+
+        // The specifier as written in the code, if it was rewritten by an alias or import map.
+        if (dep.original_specifier) {
+          meta.originalSpecifier = dep.original_specifier;
+        }
+
+        // Names imported from the dependency, or `*` if all of them may be used.
+        if (dep.imported_symbols.length > 0) {
+          meta.importedSymbols = dep.imported_symbols;
+        }
+
+        // Imports that don't bind any names, e.g. polyfills.
+        if (dep.is_side_effect_only) {
+          meta.isSideEffectOnly = true;
+        }
+
+        // Separate locations of the specifier and its statement, e.g. for code frames and
+        // quick fixes. Byte offsets are relative to the code passed to the transformer.
+        meta.specifierLoc = convertLoc(dep.specifier_loc);
+        meta.statementLoc = convertLoc(dep.statement_loc);
+        if (dep.specifier_range) {
+          meta.specifierRange = dep.specifier_range;
+        }
+        if (dep.statement_range) {
+          meta.statementRange = dep.statement_range;
+        }
+
+        // Glob dependencies are expanded into a map of the matched files by the glob resolver.
+        if (dep.glob) {
+          meta.glob = dep.glob;
+        }
+
         let env;
         if (dep.kind === 'DynamicImport') {
           // https://html.spec.whatwg.org/multipage/webappapis.html#hostimportmoduledynamically(referencingscriptormodule,-modulerequest,-promisecapability)
@@ -689,11 +885,35 @@ export default (new Transformer({
           };
         }
 
+        // Inline data URLs as separate assets, referenced by a unique key.
+        let dataURL =
+          dep.specifier_type === 'data' && config?.inlineDataURLs
+            ? parseDataURL(dep.specifier)
+            : null;
+        if (dataURL) {
+          let uniqueKey = `${asset.id}:data:${inlineAssets.length}`;
+          meta.placeholder = dep.placeholder ?? dep.specifier;
+          inlineAssets.push({
+            type: dataURL.type,
+            content: dataURL.content,
+            uniqueKey,
+          });
+          asset.addDependency({
+            specifier: uniqueKey,
+            specifierType: 'esm',
+            loc: convertLoc(dep.loc),
+            priority: dep.priority,
+            meta,
+            env,
+          });
+          continue;
+        }
+
         asset.addDependency({
           specifier: dep.specifier,
           specifierType: dep.kind === 'Require' ? 'commonjs' : 'esm',
           loc: convertLoc(dep.loc),
-          priority: dep.kind === 'DynamicImport' ? 'lazy' : 'sync',
+          priority: dep.priority,
           isOptional: dep.is_optional,
           meta,
           resolveFrom: isHelper ? __filename : undefined,
@@ -865,10 +1085,27 @@ export default (new Transformer({
       asset.setMap(sourceMap);
     }
 
-    return [asset];
+    return [asset, ...inlineAssets];
   },
 }): Transformer);
 
+function parseDataURL(url: string): ?{|type: string, content: string|} {
+  let match = url.match(/^data:([^,;]*)((?:;[^,;]*)*),([\s\S]*)$/);
+  if (!match) {
+    return null;
+  }
+
+  let type = DATA_URL_TYPES[match[1].toLowerCase()];
+  if (!type) {
+    return null;
+  }
+
+  let content = match[2].split(';').includes('base64')
+    ? Buffer.from(match[3], 'base64').toString()
+    : decodeURIComponent(match[3]);
+  return {type, content};
+}
+
 // On linux with older versions of glibc (e.g. CentOS 7), we encounter a segmentation fault
 // when worker threads exit due to thread local variables used by SWC. A workaround is to
 // also load the native module on the main thread, so that it is not unloaded until process exit.